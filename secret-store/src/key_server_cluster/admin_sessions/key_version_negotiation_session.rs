@@ -208,6 +208,13 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 			.clone())
 	}
 
+	/// Is session waiting for response from given node?
+	pub fn is_waiting_for(&self, node: &NodeId) -> bool {
+		self.data.lock().confirmations.as_ref()
+			.map(|confirmations| confirmations.contains(node))
+			.unwrap_or(false)
+	}
+
 	/// Set continue action.
 	pub fn set_continue_action(&self, action: ContinueAction) {
 		self.data.lock().continue_with = Some(action);
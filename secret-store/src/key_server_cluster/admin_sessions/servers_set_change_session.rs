@@ -15,11 +15,14 @@
 // along with Parity Ethereum.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::sync::Arc;
+use std::cmp::Reverse;
+use std::time::Duration;
 use std::collections::{BTreeSet, BTreeMap};
 use std::collections::btree_map::Entry;
 use parking_lot::{Mutex, Condvar};
 use ethereum_types::H256;
-use ethkey::{Public, Signature};
+use ethkey::{Public, Signature, recover};
+use hash::keccak;
 use key_server_cluster::{Error, NodeId, SessionId, KeyStorage};
 use key_server_cluster::math;
 use key_server_cluster::cluster::Cluster;
@@ -36,14 +39,15 @@ use key_server_cluster::key_version_negotiation_session::{SessionImpl as KeyVers
 	SessionParams as KeyVersionNegotiationSessionParams, LargestSupportResultComputer,
 	SessionTransport as KeyVersionNegotiationTransport};
 use key_server_cluster::jobs::job_session::JobTransport;
-use key_server_cluster::jobs::servers_set_change_access_job::{ServersSetChangeAccessJob, ServersSetChangeAccessRequest};
+use key_server_cluster::jobs::servers_set_change_access_job::{ServersSetChangeAccessJob, ServersSetChangeAccessRequest,
+	ordered_nodes_hash};
 use key_server_cluster::jobs::unknown_sessions_job::{UnknownSessionsJob};
 use key_server_cluster::jobs::consensus_session::{ConsensusSessionParams, ConsensusSessionState, ConsensusSession};
 use key_server_cluster::admin_sessions::sessions_queue::SessionsQueue;
 use key_server_cluster::admin_sessions::ShareChangeSessionMeta;
 
-/// Maximal number of active share change sessions.
-const MAX_ACTIVE_KEY_SESSIONS: usize = 64;
+/// Default maximal number of active share change sessions.
+pub const MAX_ACTIVE_KEY_SESSIONS: usize = 64;
 
 /// Servers set change session.
 /// Brief overview:
@@ -77,6 +81,50 @@ enum SessionState {
 	Finished,
 }
 
+/// Session state, reported to external observers. Unlike internal state, this enum is a part of public API.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PublicSessionState {
+	/// Establishing consensus.
+	EstablishingConsensus,
+	/// Running share change sessions.
+	RunningShareChangeSessions,
+	/// Session is completed (either with success or not).
+	Finished,
+}
+
+/// Session metrics. Callbacks are called outside of the session lock.
+pub trait SessionMetrics: Send + Sync {
+	/// When session is started (initialized on master node, or first message is received on slave node).
+	fn on_started(&self);
+	/// When session is completed successfully.
+	fn on_completed(&self);
+	/// When session has failed.
+	fn on_failed(&self, error: &Error);
+	/// When session state is changed. On master node, time between on_started and transition to
+	/// RunningShareChangeSessions is the consensus establishment latency.
+	fn on_state_changed(&self, from: PublicSessionState, to: PublicSessionState);
+}
+
+/// Scorer of nodes, used to select masters of share change sessions. Masters are only selected on the master
+/// node of servers set change session (other nodes accept selected masters), so scores could be local to this node.
+pub trait NodeScorer: Send + Sync {
+	/// Get node score. Key share owner with the greatest score is selected as share change session master.
+	fn score(&self, node: &NodeId) -> u64;
+}
+
+/// Reason of session completion.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FinishReason {
+	/// Session has been completed successfully.
+	Success,
+	/// Session has failed with given error.
+	Failed(Error),
+	/// Session has not been completed in time.
+	TimedOut,
+	/// Session has been cancelled by administrator.
+	Cancelled,
+}
+
 /// Immutable session data.
 struct SessionCore {
 	/// Servers set change session meta (id is computed from new_nodes_set).
@@ -89,10 +137,30 @@ struct SessionCore {
 	pub nonce: u64,
 	/// All known nodes.
 	pub all_nodes_set: BTreeSet<NodeId>,
-	/// Administrator public key.
-	pub admin_public: Public,
+	/// Administrators public keys (more than one key is accepted while administrator key is rotated).
+	pub admin_publics: BTreeSet<Public>,
 	/// Migration id (if this session is a part of auto-migration process).
 	pub migration_id: Option<H256>,
+	/// Keys priorities (keys with greater priority are changed first).
+	pub key_priorities: BTreeMap<SessionId, u8>,
+	/// Maximal number of active share change sessions.
+	pub max_active_sessions: usize,
+	/// Maximal number of share change sessions, started by single dissemination pass.
+	pub dispatch_budget: Option<usize>,
+	/// Progress callback.
+	pub progress: Option<Arc<Fn(usize, usize) + Send + Sync>>,
+	/// Only compute share change sessions plans, without changing shares.
+	pub dry_run: bool,
+	/// Only change shares of these keys (all keys are changed if None).
+	pub target_keys: Option<BTreeSet<SessionId>>,
+	/// Check that all nodes are connected before starting session.
+	pub precheck_connectivity: bool,
+	/// Share change sessions masters scorer.
+	pub node_scorer: Option<Arc<NodeScorer>>,
+	/// Nodes that never receive key shares.
+	pub readonly_nodes: BTreeSet<NodeId>,
+	/// Session metrics.
+	pub metrics: Option<Arc<SessionMetrics>>,
 	/// SessionImpl completion condvar.
 	pub completed: Condvar,
 }
@@ -110,6 +178,14 @@ struct SessionData {
 	pub new_nodes_set: Option<BTreeSet<NodeId>>,
 	/// Share change sessions queue (valid on master nodes only).
 	pub sessions_queue: Option<SessionsQueue>,
+	/// Total number of share change sessions (valid on master nodes only).
+	pub total_key_sessions: Option<usize>,
+	/// Number of share change sessions, skipped because key has been removed from the storage (valid on master nodes only).
+	pub skipped_key_sessions: usize,
+	/// Last reported progress.
+	pub reported_progress: Option<(usize, usize)>,
+	/// Last state, reported to metrics.
+	pub reported_state: Option<PublicSessionState>,
 	/// Share change sessions key version negotiation.
 	pub negotiation_sessions: BTreeMap<SessionId, KeyVersionNegotiationSessionImpl<ServersSetChangeKeyVersionNegotiationTransport>>,
 	/// Share change sessions initialization state (valid on master nodes only).
@@ -118,6 +194,8 @@ struct SessionData {
 	pub delegated_key_sessions: BTreeMap<SessionId, NodeId>,
 	/// Active share change sessions.
 	pub active_key_sessions: BTreeMap<SessionId, ShareChangeSession>,
+	/// Share change sessions plans, computed in dry run mode (valid on master node only).
+	pub dry_run_plans: BTreeMap<SessionId, ShareChangeSessionPlan>,
 	/// Servers set change result.
 	pub result: Option<Result<(), Error>>,
 }
@@ -126,7 +204,9 @@ struct SessionData {
 struct SessionInitializationData {
 	/// Master node id.
 	pub master: NodeId,
-	/// Nodes that have confirmed session initialization request.
+	/// All participants of share change session (old key share owners && new owners).
+	pub participants: BTreeSet<NodeId>,
+	/// Nodes that have not yet confirmed session initialization request.
 	pub confirmations: BTreeSet<NodeId>,
 }
 
@@ -142,10 +222,79 @@ pub struct SessionParams {
 	pub nonce: u64,
 	/// All known nodes.
 	pub all_nodes_set: BTreeSet<NodeId>,
-	/// Administrator public key.
-	pub admin_public: Public,
+	/// Administrators public keys (more than one key is accepted while administrator key is rotated).
+	pub admin_publics: BTreeSet<Public>,
 	/// Migration id (if this session is a part of auto-migration process).
 	pub migration_id: Option<H256>,
+	/// Keys priorities (keys with greater priority are changed first, keys are 0-priority by default).
+	pub key_priorities: BTreeMap<SessionId, u8>,
+	/// Maximal number of share change sessions that are running at the same time (MAX_ACTIVE_KEY_SESSIONS by default).
+	/// Greater values speed up the change of large key sets at the cost of memory && network load.
+	pub max_active_sessions: usize,
+	/// Maximal number of share change sessions that are started (on master node) when single message is processed.
+	/// Remaining sessions are started when previously started sessions are progressing. When None, sessions are
+	/// started until max_active_sessions sessions are active.
+	pub dispatch_budget: Option<usize>,
+	/// Progress callback, called on master node with (completed, total) number of share change sessions.
+	pub progress: Option<Arc<Fn(usize, usize) + Send + Sync>>,
+	/// Only compute share change sessions plans (on master node), without changing shares && key storage.
+	pub dry_run: bool,
+	/// Only change shares of these keys (on master node). When None, shares of all keys are changed.
+	/// Nodes can't be removed when only some keys are changed, because removed nodes clear their key storage.
+	pub target_keys: Option<BTreeSet<SessionId>>,
+	/// Fail fast with NodeDisconnected (on master node) if some of nodes are disconnected when session is initialized.
+	/// Otherwise disconnected nodes are only detected by timeout.
+	pub precheck_connectivity: bool,
+	/// Share change sessions masters scorer (on master node). When None, masters are spread evenly among key share owners.
+	pub node_scorer: Option<Arc<NodeScorer>>,
+	/// Nodes (from all_nodes_set) that are participating in consensus, but are never receiving new key shares.
+	/// Must be the same on all nodes, because slave nodes are checking share change plans, computed by master.
+	pub readonly_nodes: BTreeSet<NodeId>,
+	/// Session metrics.
+	pub metrics: Option<Arc<SessionMetrics>>,
+}
+
+/// Servers set change sessions configuration of this node (see SessionParams for fields description).
+#[derive(Clone)]
+pub struct ServersSetChangeConfiguration {
+	/// Maximal number of share change sessions that are running at the same time.
+	pub max_active_sessions: usize,
+	/// Maximal number of share change sessions that are started when single message is processed.
+	pub dispatch_budget: Option<usize>,
+	/// Fail fast with NodeDisconnected if some of nodes are disconnected when session is initialized.
+	pub precheck_connectivity: bool,
+	/// Share change sessions masters scorer.
+	pub node_scorer: Option<Arc<NodeScorer>>,
+	/// Nodes that are never receiving new key shares. Must be the same on all nodes.
+	pub readonly_nodes: BTreeSet<NodeId>,
+	/// Sessions metrics.
+	pub metrics: Option<Arc<SessionMetrics>>,
+}
+
+impl Default for ServersSetChangeConfiguration {
+	fn default() -> Self {
+		ServersSetChangeConfiguration {
+			max_active_sessions: MAX_ACTIVE_KEY_SESSIONS,
+			dispatch_budget: None,
+			precheck_connectivity: false,
+			node_scorer: None,
+			readonly_nodes: BTreeSet::new(),
+			metrics: None,
+		}
+	}
+}
+
+/// Options of servers set change session, started by this node (see SessionParams for fields description).
+#[derive(Clone, Default)]
+pub struct ServersSetChangeOptions {
+	/// Keys priorities.
+	pub key_priorities: BTreeMap<SessionId, u8>,
+	/// Progress callback.
+	pub progress: Option<Arc<Fn(usize, usize) + Send + Sync>>,
+	/// Only compute share change sessions plans, without changing shares.
+	pub dry_run: bool,
+	/// Only change shares of these keys.
+	pub target_keys: Option<BTreeSet<SessionId>>,
 }
 
 /// Servers set change consensus transport.
@@ -183,6 +332,21 @@ struct ServersSetChangeKeyVersionNegotiationTransport {
 impl SessionImpl {
 	/// Create new servers set change session.
 	pub fn new(params: SessionParams) -> Result<Self, Error> {
+		// at least one share change session must be active, or session will never complete
+		if params.max_active_sessions == 0 {
+			return Err(Error::InvalidNodesConfiguration);
+		}
+		if params.dispatch_budget == Some(0) {
+			return Err(Error::InvalidNodesConfiguration);
+		}
+		// this node is always a part of the cluster (this also rejects empty set)
+		if !params.all_nodes_set.contains(&params.meta.self_node_id) {
+			return Err(Error::InvalidNodesConfiguration);
+		}
+		if !params.readonly_nodes.is_subset(&params.all_nodes_set) {
+			return Err(Error::InvalidNodesConfiguration);
+		}
+
 		Ok(SessionImpl {
 			core: SessionCore {
 				meta: params.meta,
@@ -190,8 +354,18 @@ impl SessionImpl {
 				key_storage: params.key_storage,
 				nonce: params.nonce,
 				all_nodes_set: params.all_nodes_set,
-				admin_public: params.admin_public,
+				admin_publics: params.admin_publics,
 				migration_id: params.migration_id,
+				key_priorities: params.key_priorities,
+				max_active_sessions: params.max_active_sessions,
+				dispatch_budget: params.dispatch_budget,
+				progress: params.progress,
+				dry_run: params.dry_run,
+				target_keys: params.target_keys,
+				precheck_connectivity: params.precheck_connectivity,
+				node_scorer: params.node_scorer,
+				readonly_nodes: params.readonly_nodes,
+				metrics: params.metrics,
 				completed: Condvar::new(),
 			},
 			data: Mutex::new(SessionData {
@@ -199,10 +373,15 @@ impl SessionImpl {
 				consensus_session: None,
 				new_nodes_set: None,
 				sessions_queue: None,
+				total_key_sessions: None,
+				skipped_key_sessions: 0,
+				reported_progress: None,
+				reported_state: None,
 				negotiation_sessions: BTreeMap::new(),
 				sessions_initialization_state: BTreeMap::new(),
 				delegated_key_sessions: BTreeMap::new(),
 				active_key_sessions: BTreeMap::new(),
+				dry_run_plans: BTreeMap::new(),
 				result: None,
 			}),
 		})
@@ -213,21 +392,110 @@ impl SessionImpl {
 		&self.core.meta.id
 	}
 
+	/// Get session nonce.
+	pub fn nonce(&self) -> u64 {
+		self.core.nonce
+	}
+
 	/// Get migration id.
 	pub fn migration_id(&self) -> Option<&H256> {
 		self.core.migration_id.as_ref()
 	}
 
-	/// Wait for session completion.
+	/// Get new nodes set (it is known once session is initialized on master node or consensus is established on slave node).
+	pub fn new_nodes_set(&self) -> Option<BTreeSet<NodeId>> {
+		self.data.lock().new_nodes_set.clone()
+	}
+
+	/// Get plans of currently active share change sessions. Plans for queued keys are computed when key version is negotiated.
+	/// In dry run mode, plans of all keys that need to be changed are returned once session is completed.
+	pub fn planned_changes(&self) -> Vec<(SessionId, ShareChangeSessionPlan)> {
+		let data = self.data.lock();
+		data.active_key_sessions.iter()
+			.map(|(key_id, session)| (key_id.clone(), session.plan().clone()))
+			.chain(data.dry_run_plans.iter().map(|(key_id, plan)| (key_id.clone(), plan.clone())))
+			.collect()
+	}
+
+	/// Get number of share change sessions, skipped because key has been removed from the storage during the session.
+	pub fn skipped_key_sessions(&self) -> usize {
+		self.data.lock().skipped_key_sessions
+	}
+
+	/// Get nodes that have not yet confirmed initialization of share change sessions (only filled on master node).
+	pub fn pending_confirmations(&self) -> BTreeMap<SessionId, BTreeSet<NodeId>> {
+		self.data.lock().sessions_initialization_state.iter()
+			.map(|(key_id, init_data)| (key_id.clone(), init_data.confirmations.clone()))
+			.collect()
+	}
+
+	/// Get current session state.
+	pub fn status(&self) -> PublicSessionState {
+		self.data.lock().state.to_public()
+	}
+
+	/// Get reason of session completion. Returns None if session is still running.
+	pub fn finish_reason(&self) -> Option<FinishReason> {
+		let data = self.data.lock();
+		if data.state != SessionState::Finished {
+			return None;
+		}
+
+		data.result.as_ref().map(|result| match *result {
+			Ok(_) => FinishReason::Success,
+			Err(Error::Timeout) => FinishReason::TimedOut,
+			Err(Error::Cancelled) => FinishReason::Cancelled,
+			Err(ref error) => FinishReason::Failed(error.clone()),
+		})
+	}
+
+	/// Wait for session completion. When session is completed successfully, all changes made by this node
+	/// are flushed to the key storage.
 	pub fn wait(&self) -> Result<(), Error> {
 		Self::wait_session(&self.core.completed, &self.data, None, |data| data.result.clone())
 			.expect("wait_session returns Some if called without timeout; qed")
 	}
 
+	/// Wait for session completion at most `timeout` time.
+	pub fn wait_with_timeout(&self, timeout: Duration) -> Result<(), Error> {
+		Self::wait_session(&self.core.completed, &self.data, Some(timeout), |data| data.result.clone())
+			.unwrap_or(Err(Error::Timeout))
+	}
+
 	/// Initialize servers set change session on master node.
 	pub fn initialize(&self, new_nodes_set: BTreeSet<NodeId>, all_set_signature: Signature, new_set_signature: Signature) -> Result<(), Error> {
+		// session could only be initialized on master node
+		if self.core.meta.self_node_id != self.core.meta.master_node_id {
+			return Err(Error::InvalidStateForRequest);
+		}
+
 		check_nodes_set(&self.core.all_nodes_set, &new_nodes_set)?;
 
+		// nodes that are removed from the cluster clear their key storage => all keys must be moved off these nodes
+		if self.core.target_keys.is_some() && !self.core.all_nodes_set.is_subset(&new_nodes_set) {
+			return Err(Error::InvalidNodesConfiguration);
+		}
+
+		// check that change is authorized by administrator before sending any messages
+		let is_administrator = |signature: &Signature, nodes_set: &BTreeSet<NodeId>| recover(signature, &ordered_nodes_hash(nodes_set).into())
+			.map(|public| self.core.admin_publics.contains(&public))
+			.unwrap_or(false);
+		if !is_administrator(&all_set_signature, &self.core.all_nodes_set) || !is_administrator(&new_set_signature, &new_nodes_set) {
+			return Err(Error::AccessDenied);
+		}
+
+		// check that all nodes are connected before sending any messages
+		if self.core.precheck_connectivity {
+			let disconnected_nodes: BTreeSet<_> = self.core.all_nodes_set.iter()
+				.filter(|n| !self.core.cluster.is_connected(n))
+				.collect();
+			if !disconnected_nodes.is_empty() {
+				warn!(target: "secretstore_net", "{}: servers set change session can't be started: nodes {:?} are disconnected",
+					self.core.meta.self_node_id, disconnected_nodes);
+				return Err(Error::NodeDisconnected);
+			}
+		}
+
 		let mut data = self.data.lock();
 		if data.state != SessionState::EstablishingConsensus || data.consensus_session.is_some() {
 			return Err(Error::InvalidStateForRequest);
@@ -235,7 +503,7 @@ impl SessionImpl {
 
 		let mut consensus_session = ConsensusSession::new(ConsensusSessionParams {
 			meta: self.core.meta.clone().into_consensus_meta(self.core.all_nodes_set.len())?,
-			consensus_executor: ServersSetChangeAccessJob::new_on_master(self.core.admin_public.clone(),
+			consensus_executor: ServersSetChangeAccessJob::new_on_master(self.core.admin_publics.clone(),
 				self.core.all_nodes_set.clone(),
 				new_nodes_set.clone(),
 				all_set_signature,
@@ -258,17 +526,42 @@ impl SessionImpl {
 		if is_finished {
 			Self::complete_session(&self.core, &mut *data)?;
 		}
+		drop(data);
+
+		self.report_state();
+		Ok(())
+	}
+
+	/// Cancel session on master node. Other nodes are notified with Error::Cancelled and stop their share change sessions.
+	/// Share changes that have been already completed are not rolled back.
+	pub fn cancel(&self) -> Result<(), Error> {
+		if self.core.meta.self_node_id != self.core.meta.master_node_id {
+			return Err(Error::InvalidStateForRequest);
+		}
+
+		let is_cancelled = self.fail(&self.core.meta.self_node_id, Error::Cancelled, |data| {
+			// tear down all share change sessions that are started/planned on this node
+			data.sessions_queue = None;
+			data.negotiation_sessions.clear();
+			data.sessions_initialization_state.clear();
+			data.delegated_key_sessions.clear();
+			data.active_key_sessions.clear();
+		});
+		if !is_cancelled {
+			return Err(Error::InvalidStateForRequest);
+		}
 
 		Ok(())
 	}
 
 	/// Process servers set change message.
 	pub fn process_message(&self, sender: &NodeId, message: &ServersSetChangeMessage) -> Result<(), Error> {
-		if self.core.nonce != message.session_nonce() {
+		// messages of other sessions could only be received if they're replayed
+		if self.core.meta.id != *message.session_id() || self.core.nonce != message.session_nonce() {
 			return Err(Error::ReplayProtection);
 		}
 
-		match message {
+		let result = match message {
 			&ServersSetChangeMessage::ServersSetChangeConsensusMessage(ref message) =>
 				self.on_consensus_message(sender, message),
 			&ServersSetChangeMessage::UnknownSessionsRequest(ref message) =>
@@ -293,7 +586,11 @@ impl SessionImpl {
 			},
 			&ServersSetChangeMessage::ServersSetChangeCompleted(ref message) => 
 				self.on_session_completed(sender, message),
-		}
+		};
+
+		self.report_progress();
+		self.report_state();
+		result
 	}
 
 	/// When consensus-related message is received.
@@ -310,10 +607,16 @@ impl SessionImpl {
 		if self.core.meta.self_node_id != self.core.meta.master_node_id {
 			if data.consensus_session.is_none() {
 				match &message.message {
-					&ConsensusMessageWithServersSet::InitializeConsensusSession(_) => {
+					&ConsensusMessageWithServersSet::InitializeConsensusSession(ref message) => {
+						// every node computes the new set independently => check that we know all nodes of the new set
+						// (unknown node on slave means that nodes are configured differently, not that it is disconnected)
+						let new_nodes_set: BTreeSet<NodeId> = message.new_nodes_set.iter().cloned().map(Into::into).collect();
+						check_nodes_set(&self.core.all_nodes_set, &new_nodes_set)
+							.map_err(|_| Error::InvalidNodesConfiguration)?;
+
 						data.consensus_session = Some(ConsensusSession::new(ConsensusSessionParams {
 							meta: self.core.meta.clone().into_consensus_meta(self.core.all_nodes_set.len())?,
-							consensus_executor: ServersSetChangeAccessJob::new_on_slave(self.core.admin_public.clone()),
+							consensus_executor: ServersSetChangeAccessJob::new_on_slave(self.core.admin_publics.clone()),
 							consensus_transport: ServersSetChangeConsensusTransport {
 								id: self.core.meta.id.clone(),
 								nonce: self.core.nonce,
@@ -328,16 +631,43 @@ impl SessionImpl {
 		}
 
 		// process consensus message
-		let consensus_session = data.consensus_session.as_mut().ok_or(Error::InvalidMessage)?;
-		let is_establishing_consensus = consensus_session.state() == ConsensusSessionState::EstablishingConsensus;
-		match &message.message {
-			&ConsensusMessageWithServersSet::InitializeConsensusSession(ref message) =>
-				consensus_session.on_consensus_partial_request(sender, ServersSetChangeAccessRequest::from(message))?,
-			&ConsensusMessageWithServersSet::ConfirmConsensusInitialization(ref message) =>
-				consensus_session.on_consensus_partial_response(sender, message.is_confirmed)?,
+		let (is_establishing_consensus, consensus_result, is_consensus_failed) = {
+			let consensus_session = data.consensus_session.as_mut().ok_or(Error::InvalidMessage)?;
+			let is_establishing_consensus = consensus_session.state() == ConsensusSessionState::EstablishingConsensus;
+			let consensus_result = match &message.message {
+				&ConsensusMessageWithServersSet::InitializeConsensusSession(ref message) =>
+					consensus_session.on_consensus_partial_request(sender, ServersSetChangeAccessRequest::from(message)),
+				&ConsensusMessageWithServersSet::ConfirmConsensusInitialization(ref message) =>
+					consensus_session.on_consensus_partial_response(sender, message.is_confirmed),
+			};
+			(is_establishing_consensus, consensus_result, consensus_session.state() == ConsensusSessionState::Failed)
+		};
+
+		// when enough nodes have rejected consensus => it can't be established => do not wait for other responses
+		if let Err(error) = consensus_result {
+			if !is_consensus_failed {
+				return Err(error);
+			}
+
+			// all nodes must agree to change servers set => node that has denied access makes consensus unreachable
+			let is_access_denied = match &message.message {
+				&ConsensusMessageWithServersSet::ConfirmConsensusInitialization(ref message) => !message.is_confirmed,
+				_ => false,
+			};
+			let error = match error {
+				Error::ConsensusUnreachable if is_access_denied => Error::AccessDenied,
+				error => error,
+			};
+
+			// fail session on all nodes
+			drop(data);
+			self.on_session_error(&self.core.meta.self_node_id, error);
+			return Ok(());
 		}
 
 		// when consensus is established => request unknown sessions
+		let consensus_session = data.consensus_session.as_mut()
+			.expect("consensus_session is checked to be Some above; qed");
 		let is_consensus_established = consensus_session.state() == ConsensusSessionState::ConsensusEstablished;
 		if self.core.meta.self_node_id != self.core.meta.master_node_id || !is_establishing_consensus || !is_consensus_established {
 			return Ok(());
@@ -350,7 +680,9 @@ impl SessionImpl {
 	/// When unknown sessions are requested.
 	pub fn on_unknown_sessions_requested(&self, sender: &NodeId, message: &UnknownSessionsRequest) -> Result<(), Error> {
 		debug_assert!(self.core.meta.id == *message.session);
-		debug_assert!(sender != &self.core.meta.self_node_id);
+		if sender == &self.core.meta.self_node_id {
+			return Err(Error::InvalidMessage);
+		}
 
 		let mut data = self.data.lock();
 
@@ -362,9 +694,11 @@ impl SessionImpl {
 			// and respond with unknown sessions
 			consensus_session.on_job_request(&sender, sender.clone(), unknown_sessions_job, unknown_sessions_transport)?;
 
+			// new servers set is recorded when access request is processed => it could only be missing if consensus
+			// has been established in some unexpected way
 			consensus_session.consensus_job().executor()
 				.new_servers_set()
-				.expect("consensus session is now completed; new_servers_set is intermediate result of consensus session; qed")
+				.ok_or(Error::InvalidStateForRequest)?
 				.clone()
 		};
 
@@ -378,7 +712,9 @@ impl SessionImpl {
 	/// When unknown sessions are received.
 	pub fn on_unknown_sessions(&self, sender: &NodeId, message: &UnknownSessions) -> Result<(), Error> {
 		debug_assert!(self.core.meta.id == *message.session);
-		debug_assert!(sender != &self.core.meta.self_node_id);
+		if sender == &self.core.meta.self_node_id {
+			return Err(Error::InvalidMessage);
+		}
 
 		// check state
 		let mut data = self.data.lock();
@@ -401,7 +737,13 @@ impl SessionImpl {
 
 		// initialize sessions queue
 		data.state = SessionState::RunningShareChangeSessions;
-		data.sessions_queue = Some(SessionsQueue::new(&self.core.key_storage, unknown_sessions.keys().cloned().collect()));
+		let mut sessions_queue = SessionsQueue::new(&self.core.key_storage, unknown_sessions.keys().cloned().collect(),
+			self.core.key_priorities.clone());
+		if let Some(ref target_keys) = self.core.target_keys {
+			sessions_queue.retain(target_keys);
+		}
+		data.total_key_sessions = Some(sessions_queue.len());
+		data.sessions_queue = Some(sessions_queue);
 
 		// and disseminate session initialization requests
 		Self::disseminate_session_initialization_requests(&self.core, &mut *data)
@@ -410,7 +752,9 @@ impl SessionImpl {
 	/// When key version negotiation message is received.
 	pub fn on_key_version_negotiation(&self, sender: &NodeId, message: &ShareChangeKeyVersionNegotiation) -> Result<(), Error> {
 		debug_assert!(self.core.meta.id == *message.session);
-		debug_assert!(sender != &self.core.meta.self_node_id);
+		if sender == &self.core.meta.self_node_id {
+			return Err(Error::InvalidMessage);
+		}
 
 		// check state
 		let mut data = self.data.lock();
@@ -469,7 +813,9 @@ impl SessionImpl {
 	/// When share change session initialization is requested.
 	pub fn on_initialize_share_change_session(&self, sender: &NodeId, message: &InitializeShareChangeSession) -> Result<(), Error> {
 		debug_assert!(self.core.meta.id == *message.session);
-		debug_assert!(sender != &self.core.meta.self_node_id);
+		if sender == &self.core.meta.self_node_id {
+			return Err(Error::InvalidMessage);
+		}
 
 		// we only accept delegation requests from master node
 		if sender != &self.core.meta.master_node_id {
@@ -487,6 +833,14 @@ impl SessionImpl {
 		match data.active_key_sessions.contains_key(&key_id) {
 			true => return Err(Error::InvalidMessage),
 			false => {
+				// plan can't reference more nodes than there are in the cluster
+				let max_plan_nodes = self.core.all_nodes_set.len();
+				if message.version_holders.len() > max_plan_nodes
+					|| message.consensus_group.len() > max_plan_nodes
+					|| message.new_nodes_map.len() > max_plan_nodes {
+					return Err(Error::InvalidMessage);
+				}
+
 				let master_plan = ShareChangeSessionPlan {
 					key_version: message.version.clone().into(),
 					version_holders: message.version_holders.iter().cloned().map(Into::into).collect(),
@@ -499,6 +853,17 @@ impl SessionImpl {
 					return Err(Error::InvalidMessage);
 				}
 
+				// new shares could only be added to non-readonly nodes of the new set, that all nodes have agreed upon
+				// (old key share owners, including nodes being removed, are also in the map, but without id numbers)
+				{
+					let new_nodes_set = data.new_nodes_set.as_ref()
+						.expect("new_nodes_set is filled during consensus establishing; change sessions are running after this; qed");
+					if master_plan.new_nodes_map.iter().any(|(n, id)| id.is_some() &&
+						(!new_nodes_set.contains(n) || self.core.readonly_nodes.contains(n))) {
+						return Err(Error::InvalidMessage);
+					}
+				}
+
 				// on nodes, holding selected key share version, we could check if master node plan is correct
 				let master_node_id = message.master_node_id.clone().into();
 				if let Some(key_share) = self.core.key_storage.get(&key_id)? {
@@ -508,6 +873,7 @@ impl SessionImpl {
 						.expect("new_nodes_set is filled during consensus establishing; change sessions are running after this; qed");
 					let local_plan = prepare_share_change_session_plan(
 						&self.core.all_nodes_set,
+						&self.core.readonly_nodes,
 						key_share.threshold,
 						&key_id,
 						version,
@@ -518,6 +884,9 @@ impl SessionImpl {
 					if local_plan.new_nodes_map.keys().collect::<BTreeSet<_>>() != master_plan.new_nodes_map.keys().collect::<BTreeSet<_>>() {
 						return Err(Error::InvalidMessage);
 					}
+				} else if !master_plan.new_nodes_map.get(&self.core.meta.self_node_id).map(|id| id.is_some()).unwrap_or(false) {
+					// nodes that do not have key share could only receive new key share
+					return Err(Error::InvalidMessage);
 				}
 
 				let session = Self::create_share_change_session(&self.core, key_id, master_node_id, master_plan)?;
@@ -538,7 +907,9 @@ impl SessionImpl {
 	/// When share change session initialization is confirmed.
 	pub fn on_share_change_session_confirmation(&self, sender: &NodeId, message: &ConfirmShareChangeSessionInitialization) -> Result<(), Error> {
 		debug_assert!(self.core.meta.id == *message.session);
-		debug_assert!(sender != &self.core.meta.self_node_id);
+		if sender == &self.core.meta.self_node_id {
+			return Err(Error::InvalidMessage);
+		}
 
 		// we only accept delegation requests from master node
 		if self.core.meta.self_node_id != self.core.meta.master_node_id {
@@ -563,6 +934,11 @@ impl SessionImpl {
 				return Ok(());
 			}
 
+			// session could only be delegated to a node that is a part of the session
+			if !session_init_data.participants.contains(&session_init_data.master) {
+				return Err(Error::InvalidNodesConfiguration);
+			}
+
 			session_init_data.master.clone()
 		};
 
@@ -593,7 +969,9 @@ impl SessionImpl {
 	/// When sessions execution is delegated to this node.
 	pub fn on_sessions_delegation(&self, sender: &NodeId, message: &ServersSetChangeDelegate) -> Result<(), Error> {
 		debug_assert!(self.core.meta.id == *message.session);
-		debug_assert!(sender != &self.core.meta.self_node_id);
+		if sender == &self.core.meta.self_node_id {
+			return Err(Error::InvalidMessage);
+		}
 
 		// we only accept delegation requests from master node
 		if sender != &self.core.meta.master_node_id {
@@ -606,15 +984,21 @@ impl SessionImpl {
 			return Err(Error::InvalidStateForRequest);
 		}
 
-		// start session
+		// start session (only keys that are a part of this change && that we're mastering could be delegated)
 		let key_session = data.active_key_sessions.get_mut(&message.key_id.clone().into()).ok_or(Error::InvalidMessage)?;
+		if !key_session.is_master() {
+			return Err(Error::InvalidMessage);
+		}
+
 		key_session.initialize()
 	}
 
 	/// When delegated session execution is completed.
 	pub fn on_delegated_session_completed(&self, sender: &NodeId, message: &ServersSetChangeDelegateResponse) -> Result<(), Error> {
 		debug_assert!(self.core.meta.id == *message.session);
-		debug_assert!(sender != &self.core.meta.self_node_id);
+		if sender == &self.core.meta.self_node_id {
+			return Err(Error::InvalidMessage);
+		}
 
 		// we only accept delegation requests on master node
 		if self.core.meta.self_node_id != self.core.meta.master_node_id {
@@ -651,25 +1035,37 @@ impl SessionImpl {
 	/// When session completion message is received.
 	pub fn on_session_completed(&self, sender: &NodeId, message: &ServersSetChangeCompleted) -> Result<(), Error> {
 		debug_assert!(self.core.meta.id == *message.session);
-		debug_assert!(sender != &self.core.meta.self_node_id);
+
+		// master has already completed session in complete_session => ignore own broadcast, if it is echoed back
+		if sender == &self.core.meta.self_node_id {
+			return Ok(());
+		}
 
 		if sender != &self.core.meta.master_node_id {
 			return Err(Error::InvalidMessage);
 		}
 
 		let mut data = self.data.lock();
+		if data.state != SessionState::RunningShareChangeSessions {
+			return Err(Error::InvalidStateForRequest);
+		}
+
 		data.result = Some(Ok(()));
 		if data.active_key_sessions.len() != 0 {
 			return Err(Error::TooEarlyForRequest);
 		}
 
 		// if we are on the set of nodes that are being removed from the cluster, let's clear database
-		if !data.new_nodes_set.as_ref()
-			.expect("new_nodes_set is filled during initialization; session is completed after initialization; qed")
+		// (in dry run mode nothing has been changed => removed nodes are keeping their databases)
+		if !message.is_dry_run && !data.new_nodes_set.as_ref()
+			.expect("new_nodes_set is filled when entering RunningShareChangeSessions state; qed")
 			.contains(&self.core.meta.self_node_id) {
 			self.core.key_storage.clear()?;
 		}
 
+		// make sure that all key shares changes are durable before reporting completion
+		self.core.key_storage.flush()?;
+
 		data.state = SessionState::Finished;
 		self.core.completed.notify_all();
 
@@ -685,6 +1081,122 @@ impl SessionImpl {
 		}
 	}
 
+	/// Report progress of share change sessions (on master node only). Callback is called outside of the session lock.
+	fn report_progress(&self) {
+		let progress = match self.core.progress {
+			Some(ref progress) => progress,
+			None => return,
+		};
+
+		let completed_and_total = {
+			let mut data = self.data.lock();
+			let total = match data.total_key_sessions {
+				Some(total) => total,
+				None => return,
+			};
+
+			let active_key_sessions: BTreeSet<_> = data.negotiation_sessions.keys()
+				.chain(data.sessions_initialization_state.keys())
+				.chain(data.delegated_key_sessions.keys())
+				.chain(data.active_key_sessions.keys())
+				.collect();
+			let queued_key_sessions = data.sessions_queue.as_ref().map(|q| q.len()).unwrap_or_default();
+			let completed_and_total = (total.saturating_sub(active_key_sessions.len() + queued_key_sessions), total);
+			if data.reported_progress == Some(completed_and_total) {
+				return;
+			}
+
+			data.reported_progress = Some(completed_and_total);
+			completed_and_total
+		};
+
+		progress(completed_and_total.0, completed_and_total.1);
+	}
+
+	/// Finish session with given error, unless it is already finished. Returns true if session has been finished.
+	fn fail<F: FnOnce(&mut SessionData)>(&self, node: &NodeId, error: Error, tear_down: F) -> bool {
+		{
+			let mut data = self.data.lock();
+			// late errors (like timeouts) must not override result of already finished session
+			if data.state == SessionState::Finished {
+				return false;
+			}
+
+			warn!(target: "secretstore_net", "{}: servers set change session failed: {} on {}",
+				self.core.meta.self_node_id, error, node);
+
+			tear_down(&mut *data);
+			data.state = SessionState::Finished;
+			data.result = Some(Err(error.clone()));
+			self.core.completed.notify_all();
+		}
+
+		// error in generation session is considered fatal
+		// => broadcast error if error occured on this node
+		if *node == self.core.meta.self_node_id {
+			// do not bother processing send error, as we already processing error
+			let _ = self.core.cluster.broadcast(Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeError(ServersSetChangeError {
+				session: self.core.meta.id.clone().into(),
+				session_nonce: self.core.nonce,
+				error: error.into(),
+			})));
+		}
+
+		self.report_state();
+		true
+	}
+
+	/// Report session state changes to metrics. Callbacks are called outside of the session lock.
+	fn report_state(&self) {
+		let metrics = match self.core.metrics {
+			Some(ref metrics) => metrics,
+			None => return,
+		};
+
+		let (is_started, previous_state, state, result) = {
+			let mut data = self.data.lock();
+			let state = data.state.to_public();
+			if data.reported_state == Some(state) {
+				return;
+			}
+
+			let is_started = data.reported_state.is_none();
+			let previous_state = data.reported_state.unwrap_or(PublicSessionState::EstablishingConsensus);
+			data.reported_state = Some(state);
+			(is_started, previous_state, state, data.result.clone())
+		};
+
+		if is_started {
+			metrics.on_started();
+		}
+		if previous_state != state {
+			metrics.on_state_changed(previous_state, state);
+		}
+		if state == PublicSessionState::Finished {
+			match result {
+				Some(Ok(_)) => metrics.on_completed(),
+				Some(Err(ref error)) => metrics.on_failed(error),
+				None => (),
+			}
+		}
+	}
+
+	/// Is given node required to complete the session?
+	fn is_node_required(&self, node: &NodeId) -> bool {
+		let data = self.data.lock();
+		match data.state {
+			SessionState::Finished => false,
+			// every known node takes part in consensus establishing
+			SessionState::EstablishingConsensus => self.core.all_nodes_set.contains(node),
+			// only nodes that are taking part in ongoing sub-sessions are required
+			SessionState::RunningShareChangeSessions => *node == self.core.meta.master_node_id
+				|| data.negotiation_sessions.values().any(|s| s.is_waiting_for(node))
+				|| data.delegated_key_sessions.values().any(|n| n == node)
+				|| data.sessions_initialization_state.values().any(|s| s.confirmations.contains(node))
+				|| data.active_key_sessions.values().any(|s| s.is_node_required(node)),
+		}
+	}
+
 	/// When share change message is received.
 	fn on_share_change_message<F: Fn(&mut ShareChangeSession) -> Result<(), Error>>(&self, session_id: SessionId, message_processor: F) -> Result<(), Error> {
 		// check state
@@ -696,7 +1208,11 @@ impl SessionImpl {
 		// process message
 		let (is_finished, is_master) = {
 			let key_session = data.active_key_sessions.get_mut(&session_id).ok_or(Error::InvalidMessage)?;
-			message_processor(key_session)?;
+			message_processor(key_session).map_err(|error| match error {
+				// message will be processed later
+				Error::TooEarlyForRequest => error,
+				_ => Error::ShareChangeFailed(session_id.clone(), Box::new(error)),
+			})?;
 			(key_session.is_finished(), key_session.is_master())
 		};
 
@@ -732,10 +1248,17 @@ impl SessionImpl {
 			let number_of_sessions_active = data.active_key_sessions.len()
 				+ data.delegated_key_sessions.len()
 				+ data.negotiation_sessions.len();
-			let mut number_of_sessions_to_start = MAX_ACTIVE_KEY_SESSIONS.saturating_sub(number_of_sessions_active);
-			while number_of_sessions_to_start > 0 {
+			let mut number_of_sessions_to_start = core.max_active_sessions.saturating_sub(number_of_sessions_active);
+			// sessions queue is an iterator => next pass continues from where this pass has stopped
+			let mut number_of_sessions_to_dispatch = core.dispatch_budget;
+			let mut is_queue_exhausted = false;
+			while number_of_sessions_to_start > 0 && number_of_sessions_to_dispatch != Some(0) {
 				let key_id = match data.sessions_queue.as_mut().expect("checked before beginning of the loop; qed").next() {
-					None => break, // complete session
+					None => {
+						// complete session
+						is_queue_exhausted = true;
+						break;
+					},
 					Some(Err(e)) => return Err(e),
 					Some(Ok(key_id)) => key_id,
 				};
@@ -762,6 +1285,8 @@ impl SessionImpl {
 				negotiation_session.initialize(core.cluster.nodes())?;
 				if !negotiation_session.is_finished() {
 					data.negotiation_sessions.insert(key_id, negotiation_session);
+					// only sessions that are still active are charged => there's always someone to start next sessions
+					number_of_sessions_to_dispatch = number_of_sessions_to_dispatch.map(|n| n - 1);
 					continue;
 				}
 
@@ -770,10 +1295,12 @@ impl SessionImpl {
 				}
 
 				number_of_sessions_to_start = number_of_sessions_to_start - 1;
+				number_of_sessions_to_dispatch = number_of_sessions_to_dispatch.map(|n| n - 1);
 			}
+			data.skipped_key_sessions = data.sessions_queue.as_ref().map(|q| q.skipped()).unwrap_or_default();
 
 			// if iteration is not yet finished => return
-			if number_of_sessions_to_start == 0 {
+			if !is_queue_exhausted {
 				return Ok(());
 			}
 		}
@@ -796,10 +1323,12 @@ impl SessionImpl {
 		// get selected version && old nodes set from key negotiation session
 		let negotiation_session = data.negotiation_sessions.remove(&key_id)
 			.expect("share change session is only initialized when negotiation is completed; qed");
-		let (selected_version, selected_master) = negotiation_session
+		let (selected_version, _) = negotiation_session
 			.wait()?
 			.expect("initialize_share_change_session is only called on share change master; negotiation session completes with some on master; qed");
 		let selected_version_holders = negotiation_session.version_holders(&selected_version)?;
+		let selected_master = select_share_change_master(&key_id, &selected_version_holders, core.node_scorer.as_ref().map(|s| &**s))
+			.expect("version is only selected when there's at least one owner; qed");
 		let selected_version_threshold = negotiation_session.key_threshold()?;
 
 		// prepare session change plan && check if something needs to be changed
@@ -807,6 +1336,7 @@ impl SessionImpl {
 		let new_nodes_set = data.new_nodes_set.as_ref()
 			.expect("this method is called after consensus estabished; new_nodes_set is a result of consensus session; qed");
 		let session_plan = prepare_share_change_session_plan(&core.all_nodes_set,
+			&core.readonly_nodes,
 			selected_version_threshold,
 			&key_id,
 			selected_version.clone(),
@@ -817,8 +1347,18 @@ impl SessionImpl {
 			return Ok(false);
 		}
 
+		debug!(target: "secretstore_net", "{}: share change session plan for key {}: {:?}",
+			core.meta.self_node_id, key_id, session_plan);
+
+		// in dry run mode we only remember the plan
+		if core.dry_run {
+			data.dry_run_plans.insert(key_id, session_plan);
+			return Ok(false);
+		}
+
 		// send key session initialization requests
-		let mut confirmations: BTreeSet<_> = session_plan.new_nodes_map.keys().cloned().collect();
+		let participants: BTreeSet<_> = session_plan.new_nodes_map.keys().cloned().collect();
+		let mut confirmations = participants.clone();
 		let need_create_session = confirmations.remove(&core.meta.self_node_id);
 		let initialization_message = Message::ServersSetChange(ServersSetChangeMessage::InitializeShareChangeSession(InitializeShareChangeSession {
 			session: core.meta.id.clone().into(),
@@ -852,6 +1392,7 @@ impl SessionImpl {
 		} else {
 			data.sessions_initialization_state.insert(key_id, SessionInitializationData {
 				master: selected_master,
+				participants: participants,
 				confirmations: confirmations,
 			});
 		}
@@ -891,20 +1432,25 @@ impl SessionImpl {
 	/// Complete servers set change session.
 	fn complete_session(core: &SessionCore, data: &mut SessionData) -> Result<(), Error> {
 		debug_assert_eq!(core.meta.self_node_id, core.meta.master_node_id);
-		
+
 		// send completion notification
 		core.cluster.broadcast(Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeCompleted(ServersSetChangeCompleted {
 			session: core.meta.id.clone().into(),
 			session_nonce: core.nonce,
+			is_dry_run: core.dry_run,
 		})))?;
 
 		// if we are on the set of nodes that are being removed from the cluster, let's clear database
-		if !data.new_nodes_set.as_ref()
+		// (in dry run mode nothing has been changed => removed nodes are keeping their databases)
+		if !core.dry_run && !data.new_nodes_set.as_ref()
 			.expect("new_nodes_set is filled during initialization; session is completed after initialization; qed")
 			.contains(&core.meta.self_node_id) {
 			core.key_storage.clear()?;
 		}
 
+		// make sure that all key shares changes are durable before reporting completion
+		core.key_storage.flush()?;
+
 		data.state = SessionState::Finished;
 		data.result = Some(Ok(()));
 		core.completed.notify_all();
@@ -929,39 +1475,37 @@ impl ClusterSession for SessionImpl {
 	}
 
 	fn on_session_timeout(&self) {
-		self.on_session_error(&self.core.meta.self_node_id, Error::NodeDisconnected);
+		self.on_session_error(&self.core.meta.self_node_id, Error::Timeout);
 	}
 
 	fn on_node_timeout(&self, node: &NodeId) {
+		// if node isn't required to complete the session => ignore timeout
+		if !self.is_node_required(node) {
+			return;
+		}
+
 		self.on_session_error(node, Error::NodeDisconnected);
 	}
 
 	fn on_session_error(&self, node: &NodeId, error: Error) {
-		// error in generation session is considered fatal
-		// => broadcast error if error occured on this node
-		if *node == self.core.meta.self_node_id {
-			// do not bother processing send error, as we already processing error
-			let _ = self.core.cluster.broadcast(Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeError(ServersSetChangeError {
-				session: self.core.meta.id.clone().into(),
-				session_nonce: self.core.nonce,
-				error: error.clone().into(),
-			})));
-		}
-
-		let mut data = self.data.lock();
-
-		warn!(target: "secretstore_net", "{}: servers set change session failed: {} on {}",
-			self.core.meta.self_node_id, error, node);
-
-		data.state = SessionState::Finished;
-		data.result = Some(Err(error));
-		self.core.completed.notify_all();
+		self.fail(node, error, |_| ());
 	}
 
 	fn on_message(&self, sender: &NodeId, message: &Message) -> Result<(), Error> {
 		match *message {
 			Message::ServersSetChange(ref message) => self.process_message(sender, message),
-			_ => unreachable!("cluster checks message to be correct before passing; qed"),
+			_ => Err(Error::InvalidMessage),
+		}
+	}
+}
+
+impl SessionState {
+	/// Convert to public session state.
+	fn to_public(&self) -> PublicSessionState {
+		match *self {
+			SessionState::EstablishingConsensus => PublicSessionState::EstablishingConsensus,
+			SessionState::RunningShareChangeSessions => PublicSessionState::RunningShareChangeSessions,
+			SessionState::Finished => PublicSessionState::Finished,
 		}
 	}
 }
@@ -1041,21 +1585,147 @@ fn check_nodes_set(all_nodes_set: &BTreeSet<NodeId>, new_nodes_set: &BTreeSet<No
 	}
 }
 
+/// Select master of share change session from the key version holders. Holders with the greatest score are preferred.
+/// Ties are broken by key id && holders set only, so that mastership is spread evenly among equally scored nodes.
+fn select_share_change_master(key_id: &SessionId, version_holders: &BTreeSet<NodeId>, node_scorer: Option<&NodeScorer>) -> Option<NodeId> {
+	version_holders.iter()
+		.min_by_key(|node| (Reverse(node_scorer.map(|s| s.score(node)).unwrap_or_default()), keccak(&[&key_id[..], &node[..]].concat())))
+		.cloned()
+}
+
 #[cfg(test)]
 pub mod tests {
 	use std::sync::Arc;
+	use std::time::Duration;
 	use std::collections::{VecDeque, BTreeMap, BTreeSet};
+	use parking_lot::Mutex;
+	use ethereum_types::{H256, Address};
 	use ethkey::{Random, Generator, Public, Signature, KeyPair, sign};
-	use key_server_cluster::{NodeId, SessionId, Error, KeyStorage, DummyKeyStorage};
+	use key_server_cluster::{NodeId, SessionId, Error, KeyStorage, DummyKeyStorage, DocumentKeyShare};
 	use key_server_cluster::cluster::Cluster;
 	use key_server_cluster::cluster_sessions::ClusterSession;
 	use key_server_cluster::cluster::tests::DummyCluster;
 	use key_server_cluster::generation_session::tests::{MessageLoop as GenerationMessageLoop, Node as GenerationNode, generate_nodes_ids};
-	use key_server_cluster::message::Message;
+	use key_server_cluster::message::{Message, ServersSetChangeMessage, ServersSetChangeCompleted, ServersSetChangeDelegate,
+		ServersSetChangeDelegateResponse,
+		ServersSetChangeError, ServersSetChangeShareAddMessage, ShareAddMessage, ShareAddError, UnknownSessionsRequest,
+		UnknownSessions, InitializeShareChangeSession, ConfirmShareChangeSessionInitialization, ShareChangeKeyVersionNegotiation,
+		KeyVersionNegotiationMessage, RequestKeyVersions, KeyVersions, KeyShareCommon};
+	use key_server_cluster::math;
 	use key_server_cluster::admin_sessions::ShareChangeSessionMeta;
 	use key_server_cluster::admin_sessions::share_add_session::tests::check_secret_is_preserved;
-	use key_server_cluster::jobs::servers_set_change_access_job::ordered_nodes_hash;
-	use super::{SessionImpl, SessionParams};
+	use key_server_cluster::jobs::servers_set_change_access_job::{ServersSetChangeAccessJob, ordered_nodes_hash};
+	use key_server_cluster::jobs::consensus_session::ConsensusSessionState;
+	use key_server_cluster::key_version_negotiation_session::{SessionImpl as KeyVersionNegotiationSessionImpl,
+		SessionParams as KeyVersionNegotiationSessionParams, LargestSupportResultComputer};
+	use super::{SessionImpl, SessionParams, SessionState, PublicSessionState, FinishReason,
+		SessionMetrics, NodeScorer, ServersSetChangeKeyVersionNegotiationTransport, MAX_ACTIVE_KEY_SESSIONS,
+		select_share_change_master};
+
+	#[derive(Default)]
+	struct RecordingMetrics {
+		pub events: Mutex<Vec<String>>,
+	}
+
+	impl SessionMetrics for RecordingMetrics {
+		fn on_started(&self) {
+			self.events.lock().push("started".into());
+		}
+
+		fn on_completed(&self) {
+			self.events.lock().push("completed".into());
+		}
+
+		fn on_failed(&self, error: &Error) {
+			self.events.lock().push(format!("failed: {}", error));
+		}
+
+		fn on_state_changed(&self, from: PublicSessionState, to: PublicSessionState) {
+			self.events.lock().push(format!("{:?} -> {:?}", from, to));
+		}
+	}
+
+	struct FlushTrackingKeyStorage {
+		pub storage: Arc<DummyKeyStorage>,
+		pub is_flushed: Mutex<bool>,
+	}
+
+	impl KeyStorage for FlushTrackingKeyStorage {
+		fn insert(&self, document: SessionId, key: DocumentKeyShare) -> Result<(), Error> {
+			*self.is_flushed.lock() = false;
+			self.storage.insert(document, key)
+		}
+
+		fn update(&self, document: SessionId, key: DocumentKeyShare) -> Result<(), Error> {
+			*self.is_flushed.lock() = false;
+			self.storage.update(document, key)
+		}
+
+		fn get(&self, document: &SessionId) -> Result<Option<DocumentKeyShare>, Error> {
+			self.storage.get(document)
+		}
+
+		fn remove(&self, document: &SessionId) -> Result<(), Error> {
+			*self.is_flushed.lock() = false;
+			self.storage.remove(document)
+		}
+
+		fn clear(&self) -> Result<(), Error> {
+			*self.is_flushed.lock() = false;
+			self.storage.clear()
+		}
+
+		fn contains(&self, document: &SessionId) -> bool {
+			self.storage.contains(document)
+		}
+
+		fn iter<'a>(&'a self) -> Box<Iterator<Item=(SessionId, DocumentKeyShare)> + 'a> {
+			self.storage.iter()
+		}
+
+		fn flush(&self) -> Result<(), Error> {
+			*self.is_flushed.lock() = true;
+			Ok(())
+		}
+	}
+
+	struct UpdateFailingKeyStorage {
+		pub storage: Arc<DummyKeyStorage>,
+	}
+
+	impl KeyStorage for UpdateFailingKeyStorage {
+		fn insert(&self, document: SessionId, key: DocumentKeyShare) -> Result<(), Error> {
+			self.storage.insert(document, key)
+		}
+
+		fn update(&self, _document: SessionId, _key: DocumentKeyShare) -> Result<(), Error> {
+			Err(Error::Database("update failed".into()))
+		}
+
+		fn get(&self, document: &SessionId) -> Result<Option<DocumentKeyShare>, Error> {
+			self.storage.get(document)
+		}
+
+		fn remove(&self, document: &SessionId) -> Result<(), Error> {
+			self.storage.remove(document)
+		}
+
+		fn clear(&self) -> Result<(), Error> {
+			self.storage.clear()
+		}
+
+		fn contains(&self, document: &SessionId) -> bool {
+			self.storage.contains(document)
+		}
+
+		fn iter<'a>(&'a self) -> Box<Iterator<Item=(SessionId, DocumentKeyShare)> + 'a> {
+			self.storage.iter()
+		}
+
+		fn flush(&self) -> Result<(), Error> {
+			Ok(())
+		}
+	}
 
 	struct Node {
 		pub cluster: Arc<DummyCluster>,
@@ -1064,6 +1734,8 @@ pub mod tests {
 	}
 
 	struct MessageLoop {
+		pub master_node_id: NodeId,
+		pub nodes_to_add: BTreeSet<NodeId>,
 		pub admin_key_pair: KeyPair,
 		pub original_key_pair: KeyPair,
 		pub all_nodes_set: BTreeSet<NodeId>,
@@ -1074,17 +1746,35 @@ pub mod tests {
 		pub queue: VecDeque<(NodeId, NodeId, Message)>,
 	}
 
-	fn create_session(mut meta: ShareChangeSessionMeta, self_node_id: NodeId, admin_public: Public, all_nodes_set: BTreeSet<NodeId>, cluster: Arc<Cluster>, key_storage: Arc<KeyStorage>) -> SessionImpl {
+	fn create_session(meta: ShareChangeSessionMeta, self_node_id: NodeId, admin_public: Public, all_nodes_set: BTreeSet<NodeId>, cluster: Arc<Cluster>, key_storage: Arc<KeyStorage>) -> SessionImpl {
+		create_session_with_admins(meta, self_node_id, ::std::iter::once(admin_public).collect(), all_nodes_set, cluster, key_storage)
+	}
+
+	fn create_session_with_admins(mut meta: ShareChangeSessionMeta, self_node_id: NodeId, admin_publics: BTreeSet<Public>, all_nodes_set: BTreeSet<NodeId>, cluster: Arc<Cluster>, key_storage: Arc<KeyStorage>) -> SessionImpl {
 		meta.self_node_id = self_node_id;
-		SessionImpl::new(SessionParams {
+		SessionImpl::new(default_params(meta, admin_publics, all_nodes_set, cluster, key_storage)).unwrap()
+	}
+
+	fn default_params(meta: ShareChangeSessionMeta, admin_publics: BTreeSet<Public>, all_nodes_set: BTreeSet<NodeId>, cluster: Arc<Cluster>, key_storage: Arc<KeyStorage>) -> SessionParams {
+		SessionParams {
 			meta: meta,
 			all_nodes_set: all_nodes_set,
 			cluster: cluster,
 			key_storage: key_storage,
 			nonce: 1,
-			admin_public: admin_public,
+			admin_publics: admin_publics,
 			migration_id: None,
-		}).unwrap()
+			key_priorities: BTreeMap::new(),
+			max_active_sessions: MAX_ACTIVE_KEY_SESSIONS,
+			dispatch_budget: None,
+			progress: None,
+			dry_run: false,
+			target_keys: None,
+			precheck_connectivity: false,
+			node_scorer: None,
+			readonly_nodes: BTreeSet::new(),
+			metrics: None,
+		}
 	}
 
 	fn create_node(meta: ShareChangeSessionMeta, admin_public: Public, all_nodes_set: BTreeSet<NodeId>, node: &GenerationNode) -> Node {
@@ -1131,7 +1821,13 @@ pub mod tests {
 				connected_nodes_count: all_nodes_set.len(),
 			};
 
-			let old_nodes = gml.nodes.iter().map(|n| create_node(meta.clone(), admin_public.clone(), all_nodes_set.clone(), n.1));
+			let old_nodes = gml.nodes.iter().map(|n| {
+				// isolated node still considers itself a part of the cluster
+				let mut node_all_nodes_set = all_nodes_set.clone();
+				node_all_nodes_set.insert(n.0.clone());
+				create_node(meta.clone(), admin_public.clone(), node_all_nodes_set, n.1)
+			});
+			let nodes_to_add = new_nodes_ids.clone();
 			let new_nodes = new_nodes_ids.into_iter().map(|new_node_id| {
 				let new_node_cluster = Arc::new(DummyCluster::new(new_node_id.clone()));
 				for node in &all_nodes_set {
@@ -1158,6 +1854,8 @@ pub mod tests {
 			let new_set_signature = sign(admin_key_pair.secret(), &ordered_nodes_hash(&new_nodes_set)).unwrap();
 
 			MessageLoop {
+				master_node_id: master_node_id,
+				nodes_to_add: nodes_to_add,
 				admin_key_pair: admin_key_pair,
 				original_key_pair: original_key_pair,
 				all_nodes_set: all_nodes_set.clone(),
@@ -1169,60 +1867,1396 @@ pub mod tests {
 			}
 		}
 
-		pub fn run(&mut self) {
+		/// Recreate session of given node, using modified default parameters.
+		pub fn update_node_params<F: FnOnce(SessionParams) -> SessionParams>(&mut self, node_id: &NodeId, update: F) {
+			let admin_publics = ::std::iter::once(self.admin_key_pair.public().clone()).collect();
+			let node = self.nodes.get_mut(node_id).unwrap();
+			let params = default_params(node.session.core.meta.clone(), admin_publics, node.session.core.all_nodes_set.clone(),
+				node.cluster.clone(), node.key_storage.clone());
+			node.session = SessionImpl::new(update(params)).unwrap();
+		}
+
+		/// Initialize session on master node, using signed new nodes set.
+		pub fn initialize(&self) -> Result<(), Error> {
+			self.nodes[&self.master_node_id].session.initialize(self.new_nodes_set.clone(),
+				self.all_set_signature.clone(), self.new_set_signature.clone())
+		}
+
+		pub fn run(&mut self) {
 			while let Some((from, to, message)) = self.take_message() {
 				self.process_message((from, to, message)).unwrap();
 			}
 		}
 
-		pub fn take_message(&mut self) -> Option<(NodeId, NodeId, Message)> {
-			self.nodes.values()
-				.filter_map(|n| n.cluster.take_message().map(|m| (n.session.core.meta.self_node_id.clone(), m.0, m.1)))
-				.nth(0)
-				.or_else(|| self.queue.pop_front())
+		/// Process messages until given condition is met.
+		pub fn run_until<F: Fn(&MessageLoop) -> bool>(&mut self, predicate: F) {
+			while !predicate(self) {
+				let message = self.take_message().expect("condition is never met");
+				self.process_message(message).unwrap();
+			}
+		}
+
+		pub fn take_message(&mut self) -> Option<(NodeId, NodeId, Message)> {
+			self.nodes.values()
+				.filter_map(|n| n.cluster.take_message().map(|m| (n.session.core.meta.self_node_id.clone(), m.0, m.1)))
+				.nth(0)
+				.or_else(|| self.queue.pop_front())
+		}
+
+		pub fn process_message(&mut self, msg: (NodeId, NodeId, Message)) -> Result<(), Error> {
+			match { match msg.2 {
+				Message::ServersSetChange(ref message) => self.nodes[&msg.1].session.process_message(&msg.0, message),
+				_ => unreachable!("only servers set change messages are expected"),
+			} } {
+				Ok(_) => Ok(()),
+				Err(Error::TooEarlyForRequest) => {
+					self.queue.push_back(msg);
+					Ok(())
+				},
+				Err(err) => Err(err),
+			}
+		}
+	}
+
+	/// Prepare session that adds single node to the 2-of-3 key. Returns master node, one of slave nodes && the loop.
+	fn prepare_single_node_addition() -> (NodeId, NodeId, MessageLoop) {
+		let gml = generate_key(1, generate_nodes_ids(3));
+		let master_node_id = gml.nodes.keys().cloned().nth(0).unwrap();
+		let slave_node_id = gml.nodes.keys().cloned().nth(1).unwrap();
+		let nodes_to_add: BTreeSet<_> = (0..1).map(|_| Random.generate().unwrap().public().clone()).collect();
+		let ml = MessageLoop::new(&gml, master_node_id.clone(), None, nodes_to_add, BTreeSet::new(), BTreeSet::new());
+		(master_node_id, slave_node_id, ml)
+	}
+
+	pub fn generate_key(threshold: usize, nodes_ids: BTreeSet<NodeId>) -> GenerationMessageLoop {
+		let mut gml = GenerationMessageLoop::with_nodes_ids(nodes_ids);
+		gml.master().initialize(Default::default(), Default::default(), false, threshold, gml.nodes.keys().cloned().collect::<BTreeSet<_>>().into()).unwrap();
+		while let Some((from, to, message)) = gml.take_message() {
+			gml.process_message((from, to, message)).unwrap();
+		}
+		gml
+	}
+
+	#[test]
+	fn node_added_using_servers_set_change() {
+		// initial 2-of-3 session
+		// insert 1 node so that it becames 2-of-4 session
+		let (master_node_id, _, mut ml) = prepare_single_node_addition();
+		ml.initialize().unwrap();
+		ml.run();
+
+		// try to recover secret for every possible combination of nodes && check that secret is the same
+		check_secret_is_preserved(ml.original_key_pair.clone(), ml.nodes.iter().map(|(k, v)| (k.clone(), v.key_storage.clone())).collect());
+
+		// check that all sessions have finished
+		assert!(ml.nodes.values().all(|n| n.session.is_finished()));
+
+		// check that waiting for finished session returns its result
+		assert!(ml.nodes.values().all(|n| n.session.wait() == Ok(())));
+	}
+
+	#[test]
+	fn node_added_preserves_threshold_reconstruction() {
+		// initial 3-of-4 session
+		let threshold = 2;
+		let gml = generate_key(threshold, generate_nodes_ids(4));
+		let master_node_id = gml.nodes.keys().cloned().nth(0).unwrap();
+		let original_key_pair = gml.compute_key_pair(threshold);
+
+		// insert 1 node so that it becames 3-of-5 session
+		let nodes_to_add: BTreeSet<_> = (0..1).map(|_| Random.generate().unwrap().public().clone()).collect();
+		let mut ml = MessageLoop::new(&gml, master_node_id, Some(original_key_pair.clone()), nodes_to_add, BTreeSet::new(), BTreeSet::new());
+		ml.initialize().unwrap();
+		ml.run();
+
+		// every node has the same version of the key share
+		let key_versions: BTreeMap<_, _> = ml.nodes.iter()
+			.map(|(k, v)| (k.clone(), v.key_storage.get(&SessionId::default()).unwrap().unwrap().last_version().unwrap().clone()))
+			.collect();
+		let key_version_hash = key_versions.values().nth(0).unwrap().hash.clone();
+		assert!(key_versions.values().all(|v| v.hash == key_version_hash));
+
+		// joint secret could be reconstructed from every subset of threshold + 1 nodes, using id numbers known to these nodes
+		let nodes: Vec<_> = key_versions.keys().cloned().collect();
+		for subset in (0u32..1 << nodes.len()).filter(|subset| subset.count_ones() as usize == threshold + 1) {
+			let subset_nodes: Vec<_> = nodes.iter().enumerate().filter(|&(i, _)| subset & (1 << i) != 0).map(|(_, n)| n).collect();
+			let secret_shares: Vec<_> = subset_nodes.iter().map(|n| &key_versions[*n].secret_share).collect();
+			let id_numbers: Vec<_> = subset_nodes.iter().map(|n| &key_versions[*n].id_numbers[*n]).collect();
+			let joint_secret = math::compute_joint_secret_from_shares(threshold, &secret_shares, &id_numbers).unwrap();
+			assert_eq!(&joint_secret, original_key_pair.secret());
+		}
+	}
+
+	#[test]
+	fn pending_confirmations_are_reported_on_master() {
+		// initial 2-of-3 session
+		// insert 1 node so that it becames 2-of-4 session
+		let (master_node_id, _, mut ml) = prepare_single_node_addition();
+		ml.initialize().unwrap();
+
+		// run until first confirmation is received by master
+		let (from, to, message) = loop {
+			let (from, to, message) = ml.take_message().unwrap();
+			match message {
+				Message::ServersSetChange(ServersSetChangeMessage::ConfirmShareChangeSessionInitialization(_)) => break (from, to, message),
+				_ => ml.process_message((from, to, message)).unwrap(),
+			}
+		};
+
+		// master is waiting for confirmations from all other nodes
+		let other_nodes: BTreeSet<_> = ml.nodes.keys().cloned().filter(|n| *n != master_node_id).collect();
+		assert_eq!(ml.nodes[&master_node_id].session.pending_confirmations(),
+			vec![(SessionId::default(), other_nodes.clone())].into_iter().collect());
+		assert!(ml.nodes[&from].session.pending_confirmations().is_empty());
+
+		// confirmed node is removed from pending set
+		ml.process_message((from.clone(), to, message)).unwrap();
+		let mut pending_nodes = other_nodes;
+		pending_nodes.remove(&from);
+		assert_eq!(ml.nodes[&master_node_id].session.pending_confirmations(),
+			vec![(SessionId::default(), pending_nodes)].into_iter().collect());
+
+		// nothing is pending when session is completed
+		ml.run();
+		assert!(ml.nodes[&master_node_id].session.pending_confirmations().is_empty());
+	}
+
+	#[test]
+	fn unexpected_share_change_session_confirmations_are_rejected() {
+		// initial 2-of-3 session
+		// insert 1 node so that it becames 2-of-4 session
+		let (master_node_id, _, mut ml) = prepare_single_node_addition();
+		ml.initialize().unwrap();
+
+		// run until first confirmation is processed by master
+		let (from, message) = loop {
+			let (from, to, message) = ml.take_message().unwrap();
+			let confirmation = match message {
+				Message::ServersSetChange(ServersSetChangeMessage::ConfirmShareChangeSessionInitialization(ref message)) =>
+					Some(ServersSetChangeMessage::ConfirmShareChangeSessionInitialization(message.clone())),
+				_ => None,
+			};
+			ml.process_message((from.clone(), to, message)).unwrap();
+			if let Some(confirmation) = confirmation {
+				break (from, confirmation);
+			}
+		};
+		let pending_confirmations = ml.nodes[&master_node_id].session.pending_confirmations();
+
+		// node, which is not a participant of share change session, can't confirm it
+		let master_session = &ml.nodes[&master_node_id].session;
+		let foreign_node_id = Random.generate().unwrap().public().clone();
+		assert_eq!(master_session.process_message(&foreign_node_id, &message), Err(Error::InvalidMessage));
+
+		// participant can't confirm it twice
+		assert_eq!(master_session.process_message(&from, &message), Err(Error::InvalidMessage));
+
+		// confirmation of key that is not being initialized is rejected
+		let mut unknown_key_message = message.clone();
+		if let ServersSetChangeMessage::ConfirmShareChangeSessionInitialization(ref mut message) = unknown_key_message {
+			message.key_id = SessionId::from(42).into();
+		}
+		assert_eq!(master_session.process_message(&from, &unknown_key_message), Err(Error::InvalidMessage));
+
+		// pending confirmations are unaffected
+		assert_eq!(master_session.pending_confirmations(), pending_confirmations);
+	}
+
+	#[test]
+	fn share_change_session_is_not_delegated_to_non_participant() {
+		// initial 2-of-3 session
+		// insert 1 node so that it becames 2-of-4 session
+		let (master_node_id, _, mut ml) = prepare_single_node_addition();
+		ml.initialize().unwrap();
+
+		// run until master is waiting for share change session initialization confirmations
+		ml.run_until(|ml| !ml.nodes[&master_node_id].session.pending_confirmations().is_empty());
+
+		// initialization state is corrupted && names node that isn't a participant as session master
+		ml.nodes[&master_node_id].session.data.lock().sessions_initialization_state
+			.get_mut(&SessionId::default()).unwrap()
+			.master = Random.generate().unwrap().public().clone();
+
+		// => session isn't delegated when last confirmation is received
+		let error = loop {
+			let message = ml.take_message().unwrap();
+			if let Err(error) = ml.process_message(message) {
+				break error;
+			}
+		};
+		assert_eq!(error, Error::InvalidNodesConfiguration);
+		assert!(ml.nodes[&master_node_id].session.data.lock().delegated_key_sessions.is_empty());
+		while let Some((_, _, message)) = ml.take_message() {
+			match message {
+				Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeDelegate(_)) => panic!("unexpected message"),
+				_ => (),
+			}
+		}
+	}
+
+	#[test]
+	fn share_change_session_is_started_without_confirmations_when_master_is_the_only_participant() {
+		// 1-of-1 key is owned by the node, which isn't a part of master's servers set
+		let gml = generate_key(0, generate_nodes_ids(1));
+		let owner_node_id = gml.nodes.keys().cloned().nth(0).unwrap();
+		let key_version = gml.nodes[&owner_node_id].key_storage.get(&SessionId::default()).unwrap().unwrap()
+			.last_version().unwrap().hash.clone();
+
+		// => master is the only node that takes part in share change session
+		let master_node_id = Random.generate().unwrap().public().clone();
+		let master_nodes_set: BTreeSet<_> = ::std::iter::once(master_node_id.clone()).collect();
+		let cluster = Arc::new(DummyCluster::new(master_node_id.clone()));
+		cluster.add_node(owner_node_id.clone());
+		let meta = ShareChangeSessionMeta {
+			id: SessionId::default(),
+			self_node_id: master_node_id.clone(),
+			master_node_id: master_node_id.clone(),
+			configured_nodes_count: 1,
+			connected_nodes_count: 1,
+		};
+		let master_session = create_session(meta, master_node_id.clone(), Random.generate().unwrap().public().clone(),
+			master_nodes_set.clone(), cluster.clone(), Arc::new(DummyKeyStorage::default()));
+
+		// key version negotiation is waiting for owner response
+		let sub_session = math::generate_random_scalar().unwrap();
+		let negotiation_session = KeyVersionNegotiationSessionImpl::new(KeyVersionNegotiationSessionParams {
+			meta: ShareChangeSessionMeta {
+				id: SessionId::default(),
+				self_node_id: master_node_id.clone(),
+				master_node_id: master_node_id.clone(),
+				configured_nodes_count: 1,
+				connected_nodes_count: 1,
+			},
+			sub_session: sub_session.clone(),
+			key_share: None,
+			result_computer: Arc::new(LargestSupportResultComputer {}),
+			transport: ServersSetChangeKeyVersionNegotiationTransport {
+				id: SessionId::default(),
+				nonce: 1,
+				cluster: cluster.clone(),
+			},
+			nonce: 0,
+		});
+		negotiation_session.initialize(::std::iter::once(owner_node_id.clone()).collect()).unwrap();
+		while let Some(_) = cluster.take_message() {}
+		{
+			let mut data = master_session.data.lock();
+			data.state = SessionState::RunningShareChangeSessions;
+			data.new_nodes_set = Some(master_nodes_set);
+			data.negotiation_sessions.insert(SessionId::default(), negotiation_session);
+		}
+
+		// when negotiation is completed, share change session is started right away
+		master_session.process_message(&owner_node_id, &ServersSetChangeMessage::ShareChangeKeyVersionNegotiation(ShareChangeKeyVersionNegotiation {
+			session: SessionId::default().into(),
+			session_nonce: 1,
+			message: KeyVersionNegotiationMessage::KeyVersions(KeyVersions {
+				session: SessionId::default().into(),
+				sub_session: sub_session.into(),
+				session_nonce: 0,
+				threshold: Some(0),
+				versions: vec![key_version.into()],
+			}),
+		})).unwrap();
+
+		// && no confirmations round is required
+		let data = master_session.data.lock();
+		assert!(data.active_key_sessions.contains_key(&SessionId::default()));
+		assert!(data.sessions_initialization_state.is_empty());
+		while let Some((_, message)) = cluster.take_message() {
+			match message {
+				Message::ServersSetChange(ServersSetChangeMessage::InitializeShareChangeSession(_)) => panic!("unexpected message"),
+				_ => (),
+			}
+		}
+	}
+
+	#[test]
+	fn unknown_sessions_request_fails_when_new_servers_set_is_not_recorded() {
+		// initial 2-of-3 session
+		// insert 1 node so that it becames 2-of-4 session
+		let (master_node_id, slave_node_id, mut ml) = prepare_single_node_addition();
+		ml.initialize().unwrap();
+
+		// run until consensus is established on slave node
+		ml.run_until(|ml| ml.nodes[&slave_node_id].session.data.lock().consensus_session.as_ref()
+			.map(|s| s.state() == ConsensusSessionState::ConsensusEstablished)
+			.unwrap_or(false));
+
+		// forget new servers set on slave
+		{
+			let admin_public = ml.admin_key_pair.public().clone();
+			let mut data = ml.nodes[&slave_node_id].session.data.lock();
+			*data.consensus_session.as_mut().unwrap().consensus_job_mut().executor_mut() =
+				ServersSetChangeAccessJob::new_on_slave(::std::iter::once(admin_public).collect());
+		}
+
+		// => slave fails to process unknown sessions request instead of panicking
+		let (from, to, message) = loop {
+			let (from, to, message) = ml.take_message().unwrap();
+			match message {
+				Message::ServersSetChange(ServersSetChangeMessage::UnknownSessionsRequest(_)) if to == slave_node_id => break (from, to, message),
+				_ => ml.process_message((from, to, message)).unwrap(),
+			}
+		};
+		assert_eq!(ml.process_message((from, to, message)), Err(Error::InvalidStateForRequest));
+		assert_eq!(ml.nodes[&slave_node_id].session.data.lock().state, SessionState::EstablishingConsensus);
+		assert_eq!(ml.nodes[&slave_node_id].session.new_nodes_set(), None);
+	}
+
+	#[test]
+	fn master_ignores_own_completion_message() {
+		// initial 2-of-3 session
+		// insert 1 node so that it becames 2-of-4 session
+		let (master_node_id, _, mut ml) = prepare_single_node_addition();
+		ml.initialize().unwrap();
+		ml.run();
+
+		// completion broadcast is echoed back to master => it is ignored
+		let master_session = &ml.nodes[&master_node_id].session;
+		assert_eq!(master_session.process_message(&master_node_id, &ServersSetChangeMessage::ServersSetChangeCompleted(ServersSetChangeCompleted {
+			session: SessionId::default().into(),
+			session_nonce: 1,
+			is_dry_run: false,
+		})), Ok(()));
+		assert!(master_session.is_finished());
+		assert_eq!(master_session.wait(), Ok(()));
+	}
+
+	#[test]
+	fn delegation_of_unknown_key_is_rejected() {
+		// initial 2-of-3 session
+		// insert 1 node so that it becames 2-of-4 session
+		let (master_node_id, slave_node_id, mut ml) = prepare_single_node_addition();
+		ml.initialize().unwrap();
+
+		// run until slave starts running share change sessions
+		ml.run_until(|ml| ml.nodes[&slave_node_id].session.status() == PublicSessionState::RunningShareChangeSessions);
+
+		// key that has never been a part of this change is delegated to slave
+		let slave_session = &ml.nodes[&slave_node_id].session;
+		assert_eq!(slave_session.process_message(&master_node_id, &ServersSetChangeMessage::ServersSetChangeDelegate(ServersSetChangeDelegate {
+			session: SessionId::default().into(),
+			session_nonce: 1,
+			key_id: SessionId::from(42).into(),
+		})), Err(Error::InvalidMessage));
+	}
+
+	#[test]
+	fn share_change_session_of_unknown_key_is_rejected() {
+		// initial 2-of-3 session
+		// insert 1 node so that it becames 2-of-4 session
+		let (master_node_id, slave_node_id, mut ml) = prepare_single_node_addition();
+		let other_node_id = ml.nodes.keys().cloned()
+			.find(|n| *n != master_node_id && *n != slave_node_id && !ml.nodes_to_add.contains(n))
+			.unwrap();
+		ml.initialize().unwrap();
+
+		// run until slave starts running share change sessions
+		ml.run_until(|ml| ml.nodes[&slave_node_id].session.status() == PublicSessionState::RunningShareChangeSessions);
+
+		// slave doesn't have the key && it isn't going to receive new key share from the plan
+		let slave_session = &ml.nodes[&slave_node_id].session;
+		assert_eq!(slave_session.process_message(&master_node_id, &ServersSetChangeMessage::InitializeShareChangeSession(InitializeShareChangeSession {
+			session: SessionId::default().into(),
+			session_nonce: 1,
+			key_id: SessionId::from(42).into(),
+			version: H256::random().into(),
+			version_holders: vec![master_node_id.clone().into(), slave_node_id.clone().into()].into_iter().collect(),
+			master_node_id: master_node_id.clone().into(),
+			consensus_group: vec![master_node_id.clone().into(), slave_node_id.clone().into()].into_iter().collect(),
+			new_nodes_map: vec![
+				(master_node_id.clone().into(), None),
+				(slave_node_id.clone().into(), None),
+				(other_node_id.clone().into(), Some(Random.generate().unwrap().secret().clone().into())),
+			].into_iter().collect(),
+		})), Err(Error::InvalidMessage));
+		assert!(!slave_session.data.lock().active_key_sessions.contains_key(&SessionId::from(42)));
+	}
+
+	#[test]
+	fn consensus_fails_early_when_access_is_denied() {
+		// initial 2-of-3 session
+		let gml = generate_key(1, generate_nodes_ids(3));
+		let master_node_id = gml.nodes.keys().cloned().nth(0).unwrap();
+		let mut ml = MessageLoop::new(&gml, master_node_id, None, BTreeSet::new(), BTreeSet::new(), BTreeSet::new());
+
+		// slave nodes are configured with another administrator key => they deny access
+		let other_admin_public = Random.generate().unwrap().public().clone();
+		let meta = ml.nodes[&master_node_id].session.core.meta.clone();
+		let all_nodes_set = ml.all_nodes_set.clone();
+		for (node_id, node) in ml.nodes.iter_mut().filter(|&(k, _)| *k != master_node_id) {
+			node.session = create_session(meta.clone(), node_id.clone(), other_admin_public.clone(), all_nodes_set.clone(),
+				node.cluster.clone(), node.key_storage.clone());
+		}
+		ml.initialize().unwrap();
+
+		// first reject makes consensus unreachable => master fails with AccessDenied without waiting for other responses
+		ml.run_until(|ml| ml.nodes[&master_node_id].session.is_finished());
+		assert_eq!(ml.nodes[&master_node_id].session.wait(), Err(Error::AccessDenied));
+		assert_eq!(ml.nodes[&master_node_id].session.finish_reason(), Some(FinishReason::Failed(Error::AccessDenied)));
+
+		// deliver remaining messages (late responses are rejected by finished master)
+		while let Some(message) = ml.take_message() {
+			let _ = ml.process_message(message);
+		}
+
+		// && error is broadcasted to all slave nodes
+		assert!(ml.nodes.values().all(|n| n.session.wait() == Err(Error::AccessDenied)));
+	}
+
+	#[test]
+	fn unknown_sessions_messages_are_rejected_before_consensus_is_established() {
+		// initial 2-of-3 session
+		let gml = generate_key(1, generate_nodes_ids(3));
+		let master_node_id = gml.nodes.keys().cloned().nth(0).unwrap();
+		let slave_node_id = gml.nodes.keys().cloned().nth(1).unwrap();
+		let ml = MessageLoop::new(&gml, master_node_id, None, BTreeSet::new(), BTreeSet::new(), BTreeSet::new());
+
+		// slave has not yet received consensus initialization request
+		assert_eq!(ml.nodes[&slave_node_id].session.process_message(&master_node_id,
+			&ServersSetChangeMessage::UnknownSessionsRequest(UnknownSessionsRequest {
+				session: SessionId::default().into(),
+				session_nonce: 1,
+			})), Err(Error::InvalidMessage));
+
+		// master has not yet established consensus
+		ml.initialize().unwrap();
+		assert_eq!(ml.nodes[&master_node_id].session.process_message(&slave_node_id,
+			&ServersSetChangeMessage::UnknownSessions(UnknownSessions {
+				session: SessionId::default().into(),
+				session_nonce: 1,
+				unknown_sessions: BTreeSet::new(),
+			})), Err(Error::InvalidStateForRequest));
+	}
+
+	#[test]
+	fn slave_rejects_new_nodes_set_with_unknown_node() {
+		// initial 2-of-3 session
+		// insert 1 node, which is unknown to one of slave nodes
+		let (master_node_id, slave_node_id, mut ml) = prepare_single_node_addition();
+		let meta = ml.nodes[&master_node_id].session.core.meta.clone();
+		let admin_public = ml.admin_key_pair.public().clone();
+		let slave_nodes_set: BTreeSet<_> = ml.all_nodes_set.difference(&ml.nodes_to_add).cloned().collect();
+		{
+			let slave = ml.nodes.get_mut(&slave_node_id).unwrap();
+			slave.session = create_session(meta, slave_node_id.clone(), admin_public, slave_nodes_set,
+				slave.cluster.clone(), slave.key_storage.clone());
+		}
+		ml.initialize().unwrap();
+
+		// slave rejects consensus initialization
+		let mut error = None;
+		while let Some(message) = ml.take_message() {
+			if let Err(err) = ml.process_message(message) {
+				error = Some(err);
+				break;
+			}
+		}
+
+		assert_eq!(error, Some(Error::InvalidNodesConfiguration));
+		assert!(ml.nodes[&slave_node_id].session.data.lock().consensus_session.is_none());
+	}
+
+	#[test]
+	fn public_session_state_reflects_session_progress() {
+		// initial 2-of-3 session
+		// insert 1 node so that it becames 2-of-4 session
+		let (master_node_id, _, mut ml) = prepare_single_node_addition();
+		assert_eq!(ml.nodes[&master_node_id].session.status(), PublicSessionState::EstablishingConsensus);
+		ml.initialize().unwrap();
+		assert_eq!(ml.nodes[&master_node_id].session.status(), PublicSessionState::EstablishingConsensus);
+
+		// run until master starts share change sessions
+		ml.run_until(|ml| ml.nodes[&master_node_id].session.status() != PublicSessionState::EstablishingConsensus);
+		assert_eq!(ml.nodes[&master_node_id].session.status(), PublicSessionState::RunningShareChangeSessions);
+
+		ml.run();
+		assert!(ml.nodes.values().all(|n| n.session.status() == PublicSessionState::Finished));
+
+		// check internal => public state mapping
+		assert_eq!(SessionState::EstablishingConsensus.to_public(), PublicSessionState::EstablishingConsensus);
+		assert_eq!(SessionState::RunningShareChangeSessions.to_public(), PublicSessionState::RunningShareChangeSessions);
+		assert_eq!(SessionState::Finished.to_public(), PublicSessionState::Finished);
+	}
+
+	#[test]
+	fn new_nodes_set_and_planned_changes_are_exposed() {
+		// initial 2-of-3 session
+		// insert 1 node so that it becames 2-of-4 session
+		let (master_node_id, _, mut ml) = prepare_single_node_addition();
+		let new_nodes_set: BTreeSet<_> = ml.nodes.keys().cloned().collect();
+		assert_eq!(ml.nodes[&master_node_id].session.new_nodes_set(), None);
+		ml.nodes[&master_node_id].session.initialize(new_nodes_set.clone(), ml.all_set_signature.clone(), ml.new_set_signature.clone()).unwrap();
+		assert_eq!(ml.nodes[&master_node_id].session.new_nodes_set(), Some(new_nodes_set.clone()));
+
+		// run until master starts share change session
+		ml.run_until(|ml| !ml.nodes[&master_node_id].session.planned_changes().is_empty());
+
+		// check that the share is planned to be added to the new node
+		let planned_changes = ml.nodes[&master_node_id].session.planned_changes();
+		assert_eq!(planned_changes.len(), 1);
+		assert_eq!(planned_changes[0].0, SessionId::default());
+		assert!(ml.nodes_to_add.iter().all(|n| planned_changes[0].1.new_nodes_map.contains_key(n)));
+
+		// when session is completed, there are no planned changes
+		ml.run();
+		assert!(ml.nodes.values().all(|n| n.session.planned_changes().is_empty()));
+		assert!(ml.nodes.values().all(|n| n.session.new_nodes_set() == Some(new_nodes_set.clone())));
+	}
+
+	#[test]
+	fn progress_is_reported_on_master_node() {
+		// initial 2-of-3 session
+		// insert 1 node so that it becames 2-of-4 session
+		let (master_node_id, _, mut ml) = prepare_single_node_addition();
+
+		// recreate master session with progress callback
+		let reported_progress = Arc::new(Mutex::new(Vec::new()));
+		let progress = reported_progress.clone();
+		ml.update_node_params(&master_node_id, move |params| SessionParams {
+			progress: Some(Arc::new(move |completed: usize, total: usize| progress.lock().push((completed, total)))),
+			..params
+		});
+
+		ml.initialize().unwrap();
+		ml.run();
+
+		// single key is changed
+		assert_eq!(*reported_progress.lock(), vec![(0, 1), (1, 1)]);
+	}
+
+	#[test]
+	fn share_change_masters_are_spread_evenly() {
+		let nodes: BTreeSet<_> = generate_nodes_ids(5);
+		let mut masters: BTreeMap<NodeId, usize> = BTreeMap::new();
+		for key_id in (0..1000u64).map(SessionId::from) {
+			let master = select_share_change_master(&key_id, &nodes, None).unwrap();
+			assert_eq!(select_share_change_master(&key_id, &nodes, None), Some(master.clone()));
+			*masters.entry(master).or_insert(0) += 1;
+		}
+
+		// every node is selected as master for ~200 keys
+		assert_eq!(masters.len(), 5);
+		assert!(masters.values().all(|count| *count > 100 && *count < 300));
+	}
+
+	#[test]
+	fn share_change_master_is_selected_by_node_score() {
+		struct FixedScores(BTreeMap<NodeId, u64>);
+
+		impl NodeScorer for FixedScores {
+			fn score(&self, node: &NodeId) -> u64 {
+				self.0.get(node).cloned().unwrap_or_default()
+			}
+		}
+
+		// two fast nodes, one average node && two slow (unscored) nodes
+		let nodes: BTreeSet<_> = generate_nodes_ids(5);
+		let fast_nodes: BTreeSet<_> = nodes.iter().cloned().take(2).collect();
+		let average_node = nodes.iter().cloned().nth(2).unwrap();
+		let scorer = FixedScores(fast_nodes.iter().cloned().map(|n| (n, 10)).chain(::std::iter::once((average_node.clone(), 5))).collect());
+
+		// only fast nodes are selected as masters && mastership is spread among them
+		let mut masters: BTreeMap<NodeId, usize> = BTreeMap::new();
+		for key_id in (0..100u64).map(SessionId::from) {
+			let master = select_share_change_master(&key_id, &nodes, Some(&scorer)).unwrap();
+			assert_eq!(select_share_change_master(&key_id, &nodes, Some(&scorer)), Some(master.clone()));
+			*masters.entry(master).or_insert(0) += 1;
+		}
+		assert_eq!(masters.keys().cloned().collect::<BTreeSet<_>>(), fast_nodes);
+
+		// when fast nodes do not hold the key, average node is selected
+		let slow_nodes: BTreeSet<_> = nodes.difference(&fast_nodes).cloned().collect();
+		assert_eq!(select_share_change_master(&SessionId::default(), &slow_nodes, Some(&scorer)), Some(average_node));
+	}
+
+	#[test]
+	fn session_is_cancelled_after_share_change_session_is_started() {
+		// initial 2-of-3 session
+		// insert 1 node so that it becames 2-of-4 session
+		let (master_node_id, _, mut ml) = prepare_single_node_addition();
+		ml.initialize().unwrap();
+
+		// run until share change session is started (either on master or on delegated node)
+		while let Some(message) = ml.take_message() {
+			ml.process_message(message).unwrap();
+
+			let data = ml.nodes[&master_node_id].session.data.lock();
+			if !data.sessions_initialization_state.is_empty() || !data.delegated_key_sessions.is_empty() || !data.active_key_sessions.is_empty() {
+				break;
+			}
+		}
+
+		// cancel session && deliver remaining messages (sessions are finished => messages are rejected)
+		assert_eq!(ml.nodes[&master_node_id].session.cancel(), Ok(()));
+		assert_eq!(ml.nodes[&master_node_id].session.cancel(), Err(Error::InvalidStateForRequest));
+		while let Some((from, to, message)) = ml.take_message() {
+			match message {
+				Message::ServersSetChange(ref message) => { let _ = ml.nodes[&to].session.process_message(&from, message); },
+				_ => unreachable!("only servers set change messages are expected"),
+			}
+		}
+
+		// check that all sessions are cancelled
+		assert!(ml.nodes.values().all(|n| n.session.is_finished()));
+		assert!(ml.nodes.values().all(|n| n.session.wait() == Err(Error::Cancelled)));
+		assert!(ml.nodes.values().all(|n| n.session.finish_reason() == Some(FinishReason::Cancelled)));
+		assert!(ml.nodes[&master_node_id].session.planned_changes().is_empty());
+	}
+
+	#[test]
+	fn late_errors_do_not_override_result_of_finished_session() {
+		// initial 2-of-3 session
+		// insert 1 node so that it becames 2-of-4 session
+		let (master_node_id, slave_node_id, mut ml) = prepare_single_node_addition();
+		ml.initialize().unwrap();
+		ml.run();
+
+		// errors that are reported after session is completed are ignored && not broadcasted
+		let master = &ml.nodes[&master_node_id];
+		master.session.on_node_timeout(&slave_node_id);
+		master.session.on_session_timeout();
+		master.session.on_session_error(&master_node_id, Error::InvalidMessage);
+		assert_eq!(master.session.cancel(), Err(Error::InvalidStateForRequest));
+		assert_eq!(master.session.wait(), Ok(()));
+		assert!(master.cluster.take_message().is_none());
+	}
+
+	#[test]
+	fn metrics_are_reported_on_state_transitions() {
+		// initial 2-of-3 session
+		// insert 1 node so that it becames 2-of-4 session
+		let (master_node_id, _, mut ml) = prepare_single_node_addition();
+
+		// recreate master session with metrics
+		let metrics = Arc::new(RecordingMetrics::default());
+		ml.update_node_params(&master_node_id, |params| SessionParams {
+			metrics: Some(metrics.clone()),
+			..params
+		});
+
+		ml.initialize().unwrap();
+		ml.run();
+
+		assert_eq!(*metrics.events.lock(), vec![
+			"started".to_owned(),
+			"EstablishingConsensus -> RunningShareChangeSessions".to_owned(),
+			"RunningShareChangeSessions -> Finished".to_owned(),
+			"completed".to_owned(),
+		]);
+	}
+
+	#[test]
+	fn dry_run_computes_plan_without_changing_key_storage() {
+		// initial 2-of-3 session
+		let gml = generate_key(1, generate_nodes_ids(3));
+		let master_node_id = gml.nodes.keys().cloned().nth(0).unwrap();
+
+		// replace 1 node with new node
+		let nodes_to_add: BTreeSet<_> = (0..1).map(|_| Random.generate().unwrap().public().clone()).collect();
+		let nodes_to_remove: BTreeSet<_> = gml.nodes.keys().cloned().skip(1).take(1).collect();
+		let mut ml = MessageLoop::new(&gml, master_node_id, None, nodes_to_add.clone(), nodes_to_remove.clone(), BTreeSet::new());
+		let new_nodes_set: BTreeSet<_> = ml.nodes.keys().cloned().filter(|n| !nodes_to_remove.contains(n)).collect();
+		let original_shares: BTreeMap<_, _> = ml.nodes.iter()
+			.map(|(k, v)| (k.clone(), v.key_storage.get(&SessionId::default()).unwrap()))
+			.collect();
+
+		// recreate master session in dry run mode
+		ml.update_node_params(&master_node_id, |params| SessionParams {
+			dry_run: true,
+			..params
+		});
+
+		ml.nodes[&master_node_id].session.initialize(new_nodes_set, ml.all_set_signature.clone(), ml.new_set_signature.clone()).unwrap();
+		ml.run();
+
+		// master session is completed && plan is available
+		assert_eq!(ml.nodes[&master_node_id].session.wait(), Ok(()));
+		let planned_changes = ml.nodes[&master_node_id].session.planned_changes();
+		assert_eq!(planned_changes.len(), 1);
+		assert_eq!(planned_changes[0].0, SessionId::default());
+		assert!(planned_changes[0].1.new_nodes_map.contains_key(nodes_to_add.iter().nth(0).unwrap()));
+
+		// session is completed on every node && storage is untouched (including removed && added nodes)
+		assert!(ml.nodes.values().all(|n| n.session.wait() == Ok(())));
+		assert!(ml.nodes.iter().all(|(k, v)| v.key_storage.get(&SessionId::default()).unwrap() == original_shares[k]));
+	}
+
+	#[test]
+	fn only_target_keys_are_changed() {
+		// initial 2-of-3 sessions of two keys
+		let nodes_ids = generate_nodes_ids(3);
+		let gml = generate_key(1, nodes_ids.clone());
+		let other_gml = generate_key(1, nodes_ids);
+		let other_key_id = SessionId::from(42);
+		for (node_id, node) in &gml.nodes {
+			let other_key_share = other_gml.nodes[node_id].key_storage.get(&SessionId::default()).unwrap().unwrap();
+			node.key_storage.insert(other_key_id.clone(), other_key_share).unwrap();
+		}
+		let master_node_id = gml.nodes.keys().cloned().nth(0).unwrap();
+
+		// insert 1 node so that it becames 2-of-4 session, but only for the first key
+		let nodes_to_add: BTreeSet<_> = (0..1).map(|_| Random.generate().unwrap().public().clone()).collect();
+		let mut ml = MessageLoop::new(&gml, master_node_id, None, nodes_to_add.clone(), BTreeSet::new(), BTreeSet::new());
+		let other_key_shares: BTreeMap<_, _> = ml.nodes.iter()
+			.map(|(k, v)| (k.clone(), v.key_storage.get(&other_key_id).unwrap()))
+			.collect();
+		ml.update_node_params(&master_node_id, |params| SessionParams {
+			target_keys: Some(::std::iter::once(SessionId::default()).collect()),
+			..params
+		});
+
+		// nodes can't be removed when only some keys are changed
+		let slave_node_id = ml.nodes.keys().cloned().filter(|n| *n != master_node_id).nth(0).unwrap();
+		let reduced_nodes_set = ml.nodes.keys().cloned().filter(|n| *n != slave_node_id).collect();
+		assert_eq!(ml.nodes[&master_node_id].session.initialize(reduced_nodes_set, ml.all_set_signature.clone(), ml.new_set_signature.clone()),
+			Err(Error::InvalidNodesConfiguration));
+
+		ml.initialize().unwrap();
+		ml.run();
+
+		// target key is shared with new node && secret is preserved
+		check_secret_is_preserved(ml.original_key_pair.clone(), ml.nodes.iter().map(|(k, v)| (k.clone(), v.key_storage.clone())).collect());
+
+		// other key is left untouched
+		assert!(ml.nodes.iter().all(|(k, v)| v.key_storage.get(&other_key_id).unwrap() == other_key_shares[k]));
+		assert!(other_key_shares[nodes_to_add.iter().nth(0).unwrap()].is_none());
+		assert!(ml.nodes.values().all(|n| n.session.is_finished()));
+	}
+
+	#[test]
+	fn dissemination_respects_dispatch_budget() {
+		// initial 2-of-3 sessions of five keys
+		let nodes_ids = generate_nodes_ids(3);
+		let gml = generate_key(1, nodes_ids.clone());
+		let other_keys_ids: Vec<_> = (1..5u64).map(SessionId::from).collect();
+		for other_key_id in &other_keys_ids {
+			let other_gml = generate_key(1, nodes_ids.clone());
+			for (node_id, node) in &gml.nodes {
+				let other_key_share = other_gml.nodes[node_id].key_storage.get(&SessionId::default()).unwrap().unwrap();
+				node.key_storage.insert(other_key_id.clone(), other_key_share).unwrap();
+			}
+		}
+		let master_node_id = gml.nodes.keys().cloned().nth(0).unwrap();
+
+		// insert 1 node so that it becames 2-of-4 session, starting at most 2 sessions at once
+		let nodes_to_add: BTreeSet<_> = (0..1).map(|_| Random.generate().unwrap().public().clone()).collect();
+		let mut ml = MessageLoop::new(&gml, master_node_id, None, nodes_to_add.clone(), BTreeSet::new(), BTreeSet::new());
+		ml.update_node_params(&master_node_id, |params| SessionParams {
+			dispatch_budget: Some(2),
+			..params
+		});
+		ml.initialize().unwrap();
+
+		// check number of keys for which key version negotiation is started when master processes single message
+		let master_cluster = ml.nodes[&master_node_id].cluster.clone();
+		let mut max_dispatched_keys = 0;
+		while let Some((from, to, message)) = ml.take_message() {
+			let is_master_message = to == master_node_id;
+			ml.process_message((from, to, message)).unwrap();
+			if !is_master_message {
+				continue;
+			}
+
+			let mut dispatched_keys = BTreeSet::new();
+			while let Some((to, message)) = master_cluster.take_message() {
+				if let Message::ServersSetChange(ServersSetChangeMessage::ShareChangeKeyVersionNegotiation(ref message)) = message {
+					if let KeyVersionNegotiationMessage::RequestKeyVersions(ref message) = message.message {
+						dispatched_keys.insert(message.session.clone());
+					}
+				}
+				ml.queue.push_back((master_node_id.clone(), to, message));
+			}
+			assert!(dispatched_keys.len() <= 2);
+			max_dispatched_keys = ::std::cmp::max(max_dispatched_keys, dispatched_keys.len());
+		}
+		assert_eq!(max_dispatched_keys, 2);
+
+		// all keys are shared with new node
+		let new_node_id = nodes_to_add.into_iter().nth(0).unwrap();
+		assert!(ml.nodes[&new_node_id].key_storage.get(&SessionId::default()).unwrap().is_some());
+		assert!(other_keys_ids.iter().all(|k| ml.nodes[&new_node_id].key_storage.get(k).unwrap().is_some()));
+		assert!(ml.nodes.values().all(|n| n.session.is_finished()));
+	}
+
+	#[test]
+	fn readonly_node_participates_in_consensus_but_receives_no_share() {
+		// initial 2-of-3 session
+		let gml = generate_key(1, generate_nodes_ids(3));
+		let master_node_id = gml.nodes.keys().cloned().nth(0).unwrap();
+
+		// insert 2 nodes, one of which is readonly
+		let nodes_to_add: BTreeSet<_> = (0..2).map(|_| Random.generate().unwrap().public().clone()).collect();
+		let readonly_node_id = nodes_to_add.iter().cloned().nth(0).unwrap();
+		let new_node_id = nodes_to_add.iter().cloned().nth(1).unwrap();
+		let mut ml = MessageLoop::new(&gml, master_node_id, None, nodes_to_add, BTreeSet::new(), BTreeSet::new());
+		let readonly_nodes: BTreeSet<_> = ::std::iter::once(readonly_node_id.clone()).collect();
+		for node_id in ml.nodes.keys().cloned().collect::<Vec<_>>() {
+			ml.update_node_params(&node_id, |params| SessionParams {
+				readonly_nodes: readonly_nodes.clone(),
+				..params
+			});
+		}
+		ml.initialize().unwrap();
+
+		// readonly node has agreed to change servers set
+		let (from, to, message) = loop {
+			let (from, to, message) = ml.take_message().unwrap();
+			match message {
+				Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeConsensusMessage(_)) if from == readonly_node_id => break (from, to, message),
+				_ => ml.process_message((from, to, message)).unwrap(),
+			}
+		};
+		ml.process_message((from, to, message)).unwrap();
+		ml.run();
+
+		// key is shared with non-readonly new node && secret is preserved
+		check_secret_is_preserved(ml.original_key_pair.clone(), ml.nodes.iter()
+			.filter(|&(k, _)| *k != readonly_node_id)
+			.map(|(k, v)| (k.clone(), v.key_storage.clone()))
+			.collect());
+		assert!(ml.nodes[&new_node_id].key_storage.get(&SessionId::default()).unwrap().is_some());
+
+		// readonly node has no key share
+		assert!(ml.nodes[&readonly_node_id].key_storage.get(&SessionId::default()).unwrap().is_none());
+		assert!(ml.nodes.values().all(|n| n.session.is_finished()));
+	}
+
+	#[test]
+	fn connectivity_precheck_fails_when_node_is_disconnected() {
+		// initial 2-of-3 session
+		// insert 1 node so that it becames 2-of-4 session
+		let (master_node_id, slave_node_id, mut ml) = prepare_single_node_addition();
+		ml.update_node_params(&master_node_id, |params| SessionParams {
+			precheck_connectivity: true,
+			..params
+		});
+
+		// master fails fast when one of nodes is disconnected
+		ml.nodes[&master_node_id].cluster.remove_node(&slave_node_id);
+		assert_eq!(ml.nodes[&master_node_id].session.initialize(ml.nodes.keys().cloned().collect(),
+			ml.all_set_signature.clone(), ml.new_set_signature.clone()), Err(Error::NodeDisconnected));
+		assert!(ml.take_message().is_none());
+
+		// and succeeds once node is connected again
+		ml.nodes[&master_node_id].cluster.add_node(slave_node_id);
+		ml.initialize().unwrap();
+		ml.run();
+		assert!(ml.nodes.values().all(|n| n.session.wait() == Ok(())));
+	}
+
+	#[test]
+	fn key_storage_is_flushed_before_session_is_completed() {
+		// initial 2-of-3 session
+		let gml = generate_key(1, generate_nodes_ids(3));
+		let master_node_id = gml.nodes.keys().cloned().nth(0).unwrap();
+
+		// replace 1 node with new node
+		let nodes_to_add: BTreeSet<_> = (0..1).map(|_| Random.generate().unwrap().public().clone()).collect();
+		let nodes_to_remove: BTreeSet<_> = gml.nodes.keys().cloned().skip(1).take(1).collect();
+		let mut ml = MessageLoop::new(&gml, master_node_id, None, nodes_to_add.clone(), nodes_to_remove.clone(), BTreeSet::new());
+		let new_nodes_set: BTreeSet<_> = ml.nodes.keys().cloned().filter(|n| !nodes_to_remove.contains(n)).collect();
+
+		// recreate sessions using key storages that are tracking flushes
+		let key_storages: BTreeMap<_, _> = ml.nodes.iter().map(|(node_id, node)| (node_id.clone(), Arc::new(FlushTrackingKeyStorage {
+			storage: node.key_storage.clone(),
+			is_flushed: Mutex::new(true),
+		}))).collect();
+		for (node_id, key_storage) in &key_storages {
+			ml.update_node_params(node_id, |params| SessionParams {
+				key_storage: key_storage.clone(),
+				..params
+			});
+		}
+
+		// every node flushes its changes before session is completed
+		ml.nodes[&master_node_id].session.initialize(new_nodes_set, ml.all_set_signature.clone(), ml.new_set_signature.clone()).unwrap();
+		while let Some(message) = ml.take_message() {
+			ml.process_message(message).unwrap();
+			assert!(ml.nodes.iter().filter(|&(_, n)| n.session.is_finished()).all(|(k, _)| *key_storages[k].is_flushed.lock()));
+		}
+
+		assert!(ml.nodes.values().all(|n| n.session.wait() == Ok(())));
+		assert!(key_storages.values().all(|s| *s.is_flushed.lock()));
+		assert!(ml.nodes[nodes_to_add.iter().nth(0).unwrap()].key_storage.contains(&SessionId::default()));
+		assert!(!ml.nodes[nodes_to_remove.iter().nth(0).unwrap()].key_storage.contains(&SessionId::default()));
+	}
+
+	#[test]
+	fn wait_with_timeout_fails_when_session_is_not_completed_in_time() {
+		// initial 2-of-3 session
+		let gml = generate_key(1, generate_nodes_ids(3));
+		let master_node_id = gml.nodes.keys().cloned().nth(0).unwrap();
+		let ml = MessageLoop::new(&gml, master_node_id, None, BTreeSet::new(), BTreeSet::new(), BTreeSet::new());
+		ml.initialize().unwrap();
+
+		// no messages are processed => session can't complete
+		assert_eq!(ml.nodes[&master_node_id].session.wait_with_timeout(Duration::from_millis(10)), Err(Error::Timeout));
+	}
+
+	#[test]
+	fn finish_reason_reflects_session_completion() {
+		// initial 2-of-3 session
+		// insert 1 node so that it becames 2-of-4 session
+		let (master_node_id, _, mut ml) = prepare_single_node_addition();
+		ml.initialize().unwrap();
+
+		// session is still running
+		assert_eq!(ml.nodes[&master_node_id].session.finish_reason(), None);
+
+		// session is completed successfully
+		ml.run();
+		assert!(ml.nodes.values().all(|n| n.session.finish_reason() == Some(FinishReason::Success)));
+
+		// session has failed, because required node has disconnected
+		let (master_node_id, slave_node_id, ml) = prepare_single_node_addition();
+		ml.initialize().unwrap();
+		ml.nodes[&master_node_id].session.on_node_timeout(&slave_node_id);
+		assert_eq!(ml.nodes[&master_node_id].session.finish_reason(), Some(FinishReason::Failed(Error::NodeDisconnected)));
+
+		// session has timed out
+		let (master_node_id, _, ml) = prepare_single_node_addition();
+		ml.initialize().unwrap();
+		ml.nodes[&master_node_id].session.on_session_timeout();
+		assert_eq!(ml.nodes[&master_node_id].session.finish_reason(), Some(FinishReason::TimedOut));
+	}
+
+	#[test]
+	fn stalled_slave_session_fails_master_session() {
+		// initial 2-of-3 session
+		let gml = generate_key(1, generate_nodes_ids(3));
+		let master_node_id = gml.nodes.keys().cloned().nth(0).unwrap();
+		let slave_node_id = gml.nodes.keys().cloned().nth(2).unwrap();
+		let ml = MessageLoop::new(&gml, master_node_id, None, BTreeSet::new(), BTreeSet::new(), BTreeSet::new());
+		ml.initialize().unwrap();
+
+		// slave hasn't received any session messages for a long time => its session is stalled
+		let slave = &ml.nodes[&slave_node_id];
+		slave.session.on_session_timeout();
+		assert_eq!(slave.session.wait(), Err(Error::Timeout));
+
+		// master learns that the session is stalled from error message
+		while let Some((to, message)) = slave.cluster.take_message() {
+			if to != master_node_id {
+				continue;
+			}
+
+			match message {
+				Message::ServersSetChange(ref message) =>
+					ml.nodes[&master_node_id].session.process_message(&slave_node_id, message).unwrap(),
+				_ => panic!("unexpected message"),
+			}
+		}
+		assert!(ml.nodes[&master_node_id].session.is_finished());
+		assert_eq!(ml.nodes[&master_node_id].session.wait(), Err(Error::Timeout));
+	}
+
+	#[test]
+	fn session_fails_on_node_timeout_only_if_node_is_required() {
+		// initial 2-of-3 session
+		// insert 1 node so that it becames 2-of-4 session
+		let (master_node_id, slave_node_id, ml) = prepare_single_node_addition();
+		ml.initialize().unwrap();
+
+		// timeout of node that is not participating in the session is ignored
+		let unknown_node_id = Random.generate().unwrap().public().clone();
+		ml.nodes[&master_node_id].session.on_node_timeout(&unknown_node_id);
+		assert!(!ml.nodes[&master_node_id].session.is_finished());
+
+		// timeout of node that is required to establish consensus fails the session
+		ml.nodes[&master_node_id].session.on_node_timeout(&slave_node_id);
+		assert!(ml.nodes[&master_node_id].session.is_finished());
+		assert_eq!(ml.nodes[&master_node_id].session.wait(), Err(Error::NodeDisconnected));
+	}
+
+	#[test]
+	fn timeout_of_irrelevant_node_is_ignored_while_running_share_change_sessions() {
+		// initial 2-of-3 session
+		// insert 1 node so that it becames 2-of-4 session
+		let (master_node_id, _, mut ml) = prepare_single_node_addition();
+		ml.initialize().unwrap();
+
+		// run until master is negotiating key versions
+		ml.run_until(|ml| !ml.nodes[&master_node_id].session.data.lock().negotiation_sessions.is_empty());
+		assert_eq!(ml.nodes[&master_node_id].session.status(), PublicSessionState::RunningShareChangeSessions);
+
+		// timeout of node that is not taking part in any sub-session is ignored
+		let unknown_node_id = Random.generate().unwrap().public().clone();
+		ml.nodes[&master_node_id].session.on_node_timeout(&unknown_node_id);
+		assert!(!ml.nodes[&master_node_id].session.is_finished());
+
+		// && session completes as usual
+		ml.run();
+		assert!(ml.nodes.values().all(|n| n.session.wait() == Ok(())));
+	}
+
+	#[test]
+	fn session_with_invalid_all_nodes_set_is_rejected() {
+		let gml = generate_key(1, generate_nodes_ids(3));
+		let master_node_id = gml.nodes.keys().cloned().nth(0).unwrap();
+		let master = &gml.nodes[&master_node_id];
+		let meta = ShareChangeSessionMeta {
+			id: SessionId::default(),
+			self_node_id: master_node_id.clone(),
+			master_node_id: master_node_id.clone(),
+			configured_nodes_count: 3,
+			connected_nodes_count: 3,
+		};
+		let create_session = |all_nodes_set: BTreeSet<NodeId>| SessionImpl::new(default_params(meta.clone(),
+			::std::iter::once(Random.generate().unwrap().public().clone()).collect(), all_nodes_set,
+			master.cluster.clone(), master.key_storage.clone()));
+
+		// empty set
+		assert_eq!(create_session(BTreeSet::new()).err(), Some(Error::InvalidNodesConfiguration));
+
+		// set without this node
+		let other_nodes = gml.nodes.keys().cloned().filter(|n| *n != master_node_id).collect();
+		assert_eq!(create_session(other_nodes).err(), Some(Error::InvalidNodesConfiguration));
+
+		// valid set
+		assert!(create_session(gml.nodes.keys().cloned().collect()).is_ok());
+	}
+
+	#[test]
+	fn session_without_active_sessions_limit_is_rejected() {
+		let gml = generate_key(1, generate_nodes_ids(3));
+		let master_node_id = gml.nodes.keys().cloned().nth(0).unwrap();
+		let master = &gml.nodes[&master_node_id];
+		let meta = ShareChangeSessionMeta {
+			id: SessionId::default(),
+			self_node_id: master_node_id.clone(),
+			master_node_id: master_node_id.clone(),
+			configured_nodes_count: 3,
+			connected_nodes_count: 3,
+		};
+		let params = || default_params(meta.clone(), ::std::iter::once(Random.generate().unwrap().public().clone()).collect(),
+			gml.nodes.keys().cloned().collect(), master.cluster.clone(), master.key_storage.clone());
+		assert_eq!(SessionImpl::new(SessionParams {
+			max_active_sessions: 0,
+			..params()
+		}).err(), Some(Error::InvalidNodesConfiguration));
+		assert_eq!(SessionImpl::new(SessionParams {
+			dispatch_budget: Some(0),
+			..params()
+		}).err(), Some(Error::InvalidNodesConfiguration));
+	}
+
+	#[test]
+	fn replayed_messages_are_rejected() {
+		// initial 2-of-3 session
+		let gml = generate_key(1, generate_nodes_ids(3));
+		let master_node_id = gml.nodes.keys().cloned().nth(0).unwrap();
+		let mut ml = MessageLoop::new(&gml, master_node_id, None, BTreeSet::new(), BTreeSet::new(), BTreeSet::new());
+		ml.initialize().unwrap();
+
+		// capture consensus initialization message
+		let (from, to, message) = ml.take_message().unwrap();
+		let message = match message {
+			Message::ServersSetChange(message) => message,
+			_ => panic!("unexpected message"),
+		};
+		ml.process_message((from, to, Message::ServersSetChange(message.clone()))).unwrap();
+		ml.run();
+		assert_eq!(ml.nodes[&to].session.wait(), Ok(()));
+
+		// message, replayed within the same session, is rejected
+		assert!(ml.nodes[&to].session.process_message(&from, &message).is_err());
+
+		// message, replayed within other session, is rejected
+		let mut other_session_message = message.clone();
+		if let ServersSetChangeMessage::ServersSetChangeConsensusMessage(ref mut message) = other_session_message {
+			message.session = SessionId::from(42).into();
 		}
+		assert_eq!(ml.nodes[&to].session.process_message(&from, &other_session_message), Err(Error::ReplayProtection));
 
-		pub fn process_message(&mut self, msg: (NodeId, NodeId, Message)) -> Result<(), Error> {
-			match { match msg.2 {
-				Message::ServersSetChange(ref message) => self.nodes[&msg.1].session.process_message(&msg.0, message),
-				_ => unreachable!("only servers set change messages are expected"),
-			} } {
-				Ok(_) => Ok(()),
-				Err(Error::TooEarlyForRequest) => {
-					self.queue.push_back(msg);
-					Ok(())
+		// session result is not affected
+		assert_eq!(ml.nodes[&to].session.wait(), Ok(()));
+	}
+
+	#[test]
+	fn share_change_session_with_tampered_plan_is_rejected() {
+		// initial 2-of-3 session
+		// insert 1 node so that it becames 2-of-4 session
+		let (master_node_id, _, mut ml) = prepare_single_node_addition();
+		ml.initialize().unwrap();
+
+		// master asks new node to add share to the node that isn't in the new set
+		loop {
+			let (from, to, mut message) = ml.take_message().unwrap();
+			let is_tampered = ml.nodes_to_add.contains(&to) && match message {
+				Message::ServersSetChange(ServersSetChangeMessage::InitializeShareChangeSession(ref mut message)) => {
+					let unknown_node_id = Random.generate().unwrap().public().clone();
+					message.new_nodes_map.insert(unknown_node_id.into(), Some(math::generate_random_scalar().unwrap().into()));
+					true
 				},
-				Err(err) => Err(err),
+				_ => false,
+			};
+
+			if is_tampered {
+				assert_eq!(ml.process_message((from, to, message)), Err(Error::InvalidMessage));
+				break;
 			}
+
+			ml.process_message((from, to, message)).unwrap();
 		}
 	}
 
-	pub fn generate_key(threshold: usize, nodes_ids: BTreeSet<NodeId>) -> GenerationMessageLoop {
-		let mut gml = GenerationMessageLoop::with_nodes_ids(nodes_ids);
-		gml.master().initialize(Default::default(), Default::default(), false, threshold, gml.nodes.keys().cloned().collect::<BTreeSet<_>>().into()).unwrap();
-		while let Some((from, to, message)) = gml.take_message() {
-			gml.process_message((from, to, message)).unwrap();
+	#[test]
+	fn share_change_session_with_oversized_plan_is_rejected() {
+		// initial 2-of-3 session
+		// insert 1 node so that it becames 2-of-4 session
+		let (master_node_id, _, mut ml) = prepare_single_node_addition();
+		ml.initialize().unwrap();
+
+		// master sends plan that references more nodes than there are in the cluster
+		let all_nodes_count = ml.all_nodes_set.len();
+		loop {
+			let (from, to, mut message) = ml.take_message().unwrap();
+			let is_tampered = ml.nodes_to_add.contains(&to) && match message {
+				Message::ServersSetChange(ServersSetChangeMessage::InitializeShareChangeSession(ref mut message)) => {
+					for _ in 0..all_nodes_count {
+						message.version_holders.insert(Random.generate().unwrap().public().clone().into());
+					}
+					true
+				},
+				_ => false,
+			};
+
+			if is_tampered {
+				assert_eq!(ml.process_message((from, to, message)), Err(Error::InvalidMessage));
+				break;
+			}
+
+			ml.process_message((from, to, message)).unwrap();
 		}
-		gml
 	}
 
 	#[test]
-	fn node_added_using_servers_set_change() {
+	fn share_change_session_error_contains_key_id() {
+		// initial 2-of-3 session
+		// insert 1 node so that it becames 2-of-4 session
+		let (master_node_id, _, mut ml) = prepare_single_node_addition();
+		ml.initialize().unwrap();
+
+		// run until share change session is started on some node
+		let node_id = loop {
+			let message = ml.take_message().unwrap();
+			ml.process_message(message).unwrap();
+
+			let node_id = ml.nodes.iter()
+				.find(|&(_, n)| !n.session.data.lock().active_key_sessions.is_empty())
+				.map(|(k, _)| k.clone());
+			if let Some(node_id) = node_id {
+				break node_id;
+			}
+		};
+
+		// share add session fails on other node
+		let other_node_id = ml.nodes.keys().cloned().find(|n| *n != node_id).unwrap();
+		let error = ml.nodes[&node_id].session.process_message(&other_node_id, &ServersSetChangeMessage::ServersSetChangeShareAddMessage(ServersSetChangeShareAddMessage {
+			session: SessionId::default().into(),
+			session_nonce: 1,
+			message: ShareAddMessage::ShareAddError(ShareAddError {
+				session: SessionId::default().into(),
+				session_nonce: 1,
+				error: Error::InvalidMessage,
+			}),
+		})).unwrap_err();
+
+		// error identifies failed key
+		assert_eq!(error, Error::ShareChangeFailed(SessionId::default(), Box::new(Error::InvalidMessage)));
+		assert!(format!("{}", error).contains(&format!("{:?}", SessionId::default())));
+	}
+
+	#[test]
+	fn bogus_delegated_session_responses_are_rejected() {
 		// initial 2-of-3 session
 		let gml = generate_key(1, generate_nodes_ids(3));
-		let master_node_id = gml.nodes.keys().cloned().nth(0).unwrap();
+
+		// master is not selected as share change session master => share change session is delegated to other owner
+		let delegate_node_id = select_share_change_master(&SessionId::default(), &gml.nodes.keys().cloned().collect(), None).unwrap();
+		let master_node_id = gml.nodes.keys().cloned().find(|n| *n != delegate_node_id).unwrap();
+		let other_node_id = gml.nodes.keys().cloned().find(|n| *n != delegate_node_id && *n != master_node_id).unwrap();
 
 		// insert 1 node so that it becames 2-of-4 session
 		let nodes_to_add: BTreeSet<_> = (0..1).map(|_| Random.generate().unwrap().public().clone()).collect();
-		let mut ml = MessageLoop::new(&gml, master_node_id, None, nodes_to_add, BTreeSet::new(), BTreeSet::new());
-		ml.nodes[&master_node_id].session.initialize(ml.nodes.keys().cloned().collect(), ml.all_set_signature.clone(), ml.new_set_signature.clone()).unwrap();
-		ml.run();
+		let mut ml = MessageLoop::new(&gml, master_node_id.clone(), None, nodes_to_add, BTreeSet::new(), BTreeSet::new());
+		ml.initialize().unwrap();
+
+		// run until master has delegated key session to the delegate node
+		ml.run_until(|ml| !ml.nodes[&master_node_id].session.data.lock().delegated_key_sessions.is_empty());
+		let master = &ml.nodes[&master_node_id];
+		assert_eq!(master.session.data.lock().delegated_key_sessions.get(&SessionId::default()), Some(&delegate_node_id));
+
+		let response = |session: SessionId, key_id: SessionId| ServersSetChangeMessage::ServersSetChangeDelegateResponse(ServersSetChangeDelegateResponse {
+			session: session.into(),
+			session_nonce: 1,
+			key_id: key_id.into(),
+		});
+
+		// response from node that isn't a delegate
+		assert_eq!(master.session.process_message(&other_node_id, &response(SessionId::default(), SessionId::default())),
+			Err(Error::InvalidMessage));
+		// response for key that hasn't been delegated
+		assert_eq!(master.session.process_message(&delegate_node_id, &response(SessionId::default(), SessionId::from(42))),
+			Err(Error::InvalidMessage));
+		// response of other servers set change session
+		assert_eq!(master.session.process_message(&delegate_node_id, &response(SessionId::from(1), SessionId::default())),
+			Err(Error::ReplayProtection));
+
+		// master still waits for delegated session
+		assert_eq!(master.session.data.lock().delegated_key_sessions.get(&SessionId::default()), Some(&delegate_node_id));
+	}
 
-		// try to recover secret for every possible combination of nodes && check that secret is the same
-		check_secret_is_preserved(ml.original_key_pair.clone(), ml.nodes.iter().map(|(k, v)| (k.clone(), v.key_storage.clone())).collect());
+	#[test]
+	fn change_signed_by_any_of_administrators_is_accepted() {
+		for admin_index in 0..2 {
+			// initial 2-of-3 session
+			// insert 1 node so that it becames 2-of-4 session
+			let (master_node_id, _, mut ml) = prepare_single_node_addition();
+
+			// administrator key is being rotated => every node accepts both old && new keys
+			let new_admin_key_pair = Random.generate().unwrap();
+			let admin_key_pair = if admin_index == 0 { ml.admin_key_pair.clone() } else { new_admin_key_pair.clone() };
+			let admin_publics: BTreeSet<_> = vec![ml.admin_key_pair.public().clone(), new_admin_key_pair.public().clone()].into_iter().collect();
+			let meta = ml.nodes[&master_node_id].session.core.meta.clone();
+			let all_nodes_set = ml.all_nodes_set.clone();
+			for (node_id, node) in ml.nodes.iter_mut() {
+				node.session = create_session_with_admins(meta.clone(), node_id.clone(), admin_publics.clone(), all_nodes_set.clone(),
+					node.cluster.clone(), node.key_storage.clone());
+			}
 
-		// check that all sessions have finished
+			// change is signed by one of administrators
+			let new_nodes_set: BTreeSet<_> = ml.nodes.keys().cloned().collect();
+			let all_set_signature = sign(admin_key_pair.secret(), &ordered_nodes_hash(&ml.all_nodes_set)).unwrap();
+			let new_set_signature = sign(admin_key_pair.secret(), &ordered_nodes_hash(&new_nodes_set)).unwrap();
+			ml.nodes[&master_node_id].session.initialize(new_nodes_set, all_set_signature, new_set_signature).unwrap();
+			ml.run();
+
+			assert!(ml.nodes.values().all(|n| n.session.wait() == Ok(())));
+		}
+	}
+
+	#[test]
+	fn initialize_fails_when_change_is_not_signed_by_administrator() {
+		// initial 2-of-3 session
+		let gml = generate_key(1, generate_nodes_ids(3));
+		let master_node_id = gml.nodes.keys().cloned().nth(0).unwrap();
+		let ml = MessageLoop::new(&gml, master_node_id, None, BTreeSet::new(), BTreeSet::new(), BTreeSet::new());
+		let new_nodes_set: BTreeSet<_> = ml.nodes.keys().cloned().collect();
+
+		// sets are signed by someone else
+		let other_key_pair = Random.generate().unwrap();
+		let other_all_set_signature = sign(other_key_pair.secret(), &ordered_nodes_hash(&ml.all_nodes_set)).unwrap();
+		let other_new_set_signature = sign(other_key_pair.secret(), &ordered_nodes_hash(&new_nodes_set)).unwrap();
+		let master = &ml.nodes[&master_node_id];
+		assert_eq!(master.session.initialize(new_nodes_set.clone(), other_all_set_signature.clone(), ml.new_set_signature.clone()),
+			Err(Error::AccessDenied));
+		assert_eq!(master.session.initialize(new_nodes_set.clone(), ml.all_set_signature.clone(), other_new_set_signature),
+			Err(Error::AccessDenied));
+
+		// nothing is sent to other nodes
+		assert!(master.cluster.take_message().is_none());
+
+		// valid signatures are accepted
+		assert_eq!(master.session.initialize(new_nodes_set, ml.all_set_signature.clone(), ml.new_set_signature.clone()), Ok(()));
+	}
+
+	#[test]
+	fn initialize_fails_on_slave_node() {
+		// initial 2-of-3 session
+		let gml = generate_key(1, generate_nodes_ids(3));
+		let master_node_id = gml.nodes.keys().cloned().nth(0).unwrap();
+		let slave_node_id = gml.nodes.keys().cloned().nth(1).unwrap();
+		let ml = MessageLoop::new(&gml, master_node_id, None, BTreeSet::new(), BTreeSet::new(), BTreeSet::new());
+		assert_eq!(ml.nodes[&slave_node_id].session.initialize(ml.nodes.keys().cloned().collect(),
+			ml.all_set_signature.clone(), ml.new_set_signature.clone()), Err(Error::InvalidStateForRequest));
+		assert!(ml.nodes[&slave_node_id].session.data.lock().consensus_session.is_none());
+	}
+
+	#[test]
+	fn slave_session_fails_when_error_is_received() {
+		// initial 2-of-3 session
+		let gml = generate_key(1, generate_nodes_ids(3));
+		let master_node_id = gml.nodes.keys().cloned().nth(0).unwrap();
+		let slave_node_id = gml.nodes.keys().cloned().nth(1).unwrap();
+		let ml = MessageLoop::new(&gml, master_node_id, None, BTreeSet::new(), BTreeSet::new(), BTreeSet::new());
+		let slave = &ml.nodes[&slave_node_id];
+
+		// error from other session is rejected
+		assert_eq!(slave.session.process_message(&master_node_id, &ServersSetChangeMessage::ServersSetChangeError(ServersSetChangeError {
+			session: SessionId::default().into(),
+			session_nonce: 2,
+			error: Error::AccessDenied,
+		})), Err(Error::ReplayProtection));
+		assert!(!slave.session.is_finished());
+
+		// error from this session fails the session
+		assert_eq!(slave.session.process_message(&master_node_id, &ServersSetChangeMessage::ServersSetChangeError(ServersSetChangeError {
+			session: SessionId::default().into(),
+			session_nonce: 1,
+			error: Error::AccessDenied,
+		})), Ok(()));
+		assert!(slave.session.is_finished());
+		assert_eq!(slave.session.wait(), Err(Error::AccessDenied));
+	}
+
+	#[test]
+	fn share_change_session_error_on_master_is_broadcasted() {
+		// initial 2-of-3 session
+		// insert 1 node, while master is unable to update its key share
+		let (master_node_id, _, mut ml) = prepare_single_node_addition();
+		let key_storage = Arc::new(UpdateFailingKeyStorage {
+			storage: ml.nodes[&master_node_id].key_storage.clone(),
+		});
+		ml.update_node_params(&master_node_id, |params| SessionParams {
+			key_storage: key_storage,
+			..params
+		});
+		ml.initialize().unwrap();
+
+		// cluster fails the session when message processing fails && drops messages of finished sessions
+		while let Some((from, to, message)) = ml.take_message() {
+			if ml.nodes[&to].session.is_finished() {
+				continue;
+			}
+
+			if let Err(error) = ml.process_message((from, to.clone(), message)) {
+				ml.nodes[&to].session.on_session_error(&to, error);
+			}
+		}
+
+		// => share change session has failed on master && error is broadcasted to all other nodes
+		let error = Error::ShareChangeFailed(SessionId::default(), Box::new(Error::Database("update failed".into())));
 		assert!(ml.nodes.values().all(|n| n.session.is_finished()));
+		assert!(ml.nodes.values().all(|n| n.session.wait() == Err(error.clone())));
+	}
+
+	#[test]
+	fn session_fails_on_session_timeout() {
+		// initial 2-of-3 session
+		let gml = generate_key(1, generate_nodes_ids(3));
+		let master_node_id = gml.nodes.keys().cloned().nth(0).unwrap();
+		let ml = MessageLoop::new(&gml, master_node_id, None, BTreeSet::new(), BTreeSet::new(), BTreeSet::new());
+		ml.initialize().unwrap();
+
+		// session has stalled => it is completed with error
+		let master = &ml.nodes[&master_node_id];
+		master.session.on_session_timeout();
+		assert!(master.session.is_finished());
+		assert_eq!(master.session.wait(), Err(Error::Timeout));
+		assert_eq!(master.session.finish_reason(), Some(FinishReason::TimedOut));
+
+		// && error is broadcasted to other nodes
+		let mut messages = Vec::new();
+		while let Some(message) = master.cluster.take_message() {
+			messages.push(message);
+		}
+		assert!(messages.iter().any(|&(_, ref message)| match *message {
+			Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeError(ref message)) => message.error == Error::Timeout,
+			_ => false,
+		}));
 	}
 
 	#[test]
@@ -1238,9 +3272,34 @@ pub mod tests {
 		let nodes_to_add: BTreeSet<_> = (0..1).map(|_| Random.generate().unwrap().public().clone()).collect();
 		let master_node_id = nodes_to_add.iter().cloned().nth(0).unwrap();
 		let mut ml = MessageLoop::new(&gml, master_node_id, None, nodes_to_add, BTreeSet::new(), BTreeSet::new());
-		ml.nodes[&master_node_id].session.initialize(ml.nodes.keys().cloned().collect(), ml.all_set_signature.clone(), ml.new_set_signature.clone()).unwrap();
+		ml.initialize().unwrap();
+		ml.run();
+
+		// check that all sessions have finished
+		assert!(ml.nodes.values().all(|n| n.session.is_finished()));
+	}
+
+	#[test]
+	fn more_nodes_added_than_removed_using_servers_set_change() {
+		// initial 2-of-3 session
+		let gml = generate_key(1, generate_nodes_ids(3));
+		let master_node_id = gml.nodes.keys().cloned().nth(0).unwrap();
+
+		// insert 3 nodes && remove nothing so that it becames 2-of-6 session
+		let nodes_to_add: BTreeSet<_> = (0..3).map(|_| Random.generate().unwrap().public().clone()).collect();
+		let mut ml = MessageLoop::new(&gml, master_node_id, None, nodes_to_add.clone(), BTreeSet::new(), BTreeSet::new());
+		ml.initialize().unwrap();
 		ml.run();
 
+		// check that all 6 nodes own key share of the same version with the same threshold
+		let key_shares: Vec<_> = ml.nodes.values().map(|n| n.key_storage.get(&SessionId::default()).unwrap().unwrap()).collect();
+		assert_eq!(key_shares.len(), 6);
+		assert!(key_shares.iter().all(|ks| ks.threshold == 1));
+		assert!(key_shares.iter().all(|ks| ks.last_version().unwrap().id_numbers.len() == 6));
+
+		// try to recover secret for every possible combination of nodes && check that secret is the same
+		check_secret_is_preserved(ml.original_key_pair.clone(), ml.nodes.iter().map(|(k, v)| (k.clone(), v.key_storage.clone())).collect());
+
 		// check that all sessions have finished
 		assert!(ml.nodes.values().all(|n| n.session.is_finished()));
 	}
@@ -1272,6 +3331,91 @@ pub mod tests {
 		assert!(ml.nodes.values().all(|n| n.session.is_finished()));
 	}
 
+	#[test]
+	fn adding_node_that_is_already_in_set_is_noop() {
+		// initial 2-of-3 session
+		let gml = generate_key(1, generate_nodes_ids(3));
+		let master_node_id = gml.nodes.keys().cloned().nth(0).unwrap();
+		let mut ml = MessageLoop::new(&gml, master_node_id, None, BTreeSet::new(), BTreeSet::new(), BTreeSet::new());
+		let old_key_shares: BTreeMap<_, _> = ml.nodes.iter()
+			.map(|(k, v)| (k.clone(), v.key_storage.get(&SessionId::default()).unwrap()))
+			.collect();
+
+		// 'add' node that is already a key share owner => new nodes set is the same as the old one
+		let already_added_node_id = gml.nodes.keys().cloned().nth(1).unwrap();
+		let new_nodes_set: BTreeSet<_> = ml.nodes.keys().cloned().chain(::std::iter::once(already_added_node_id)).collect();
+		ml.nodes[&master_node_id].session.initialize(new_nodes_set, ml.all_set_signature.clone(), ml.new_set_signature.clone()).unwrap();
+		ml.run();
+
+		// check that key shares were left untouched
+		assert!(ml.nodes.iter().all(|(k, v)| v.key_storage.get(&SessionId::default()).unwrap() == old_key_shares[k]));
+
+		// check that all sessions have finished
+		assert!(ml.nodes.values().all(|n| n.session.is_finished()));
+		assert!(ml.nodes.values().all(|n| n.session.wait() == Ok(())));
+	}
+
+	#[test]
+	fn removed_master_completes_after_delegated_sessions_are_completed() {
+		// initial 2-of-3 session
+		let gml = generate_key(1, generate_nodes_ids(3));
+
+		// master is not selected as share change session master => share change session is delegated to other owner
+		let share_change_master = select_share_change_master(&SessionId::default(), &gml.nodes.keys().cloned().collect(), None).unwrap();
+		let master_node_id = gml.nodes.keys().cloned().find(|n| n != &share_change_master).unwrap();
+
+		// remove master node && insert 1 node so that master' share is moved
+		let nodes_to_remove: BTreeSet<_> = vec![master_node_id.clone()].into_iter().collect();
+		let nodes_to_add: BTreeSet<_> = (0..1).map(|_| Random.generate().unwrap().public().clone()).collect();
+		let mut ml = MessageLoop::new(&gml, master_node_id, None, nodes_to_add.clone(), nodes_to_remove.clone(), BTreeSet::new());
+		let new_nodes_set = ml.nodes.keys().cloned().filter(|n| !nodes_to_remove.contains(n)).collect();
+		ml.nodes[&master_node_id].session.initialize(new_nodes_set, ml.all_set_signature.clone(), ml.new_set_signature.clone()).unwrap();
+		ml.run();
+
+		// check that secret is still the same as before moving the share
+		check_secret_is_preserved(ml.original_key_pair.clone(), ml.nodes.iter()
+			.filter(|&(k, _)| !nodes_to_remove.contains(k))
+			.map(|(k, v)| (k.clone(), v.key_storage.clone()))
+			.collect());
+
+		// check that master has removed its key share only after delegated session has been completed
+		assert!(ml.nodes[&master_node_id].key_storage.get(&SessionId::default()).unwrap().is_none());
+
+		// check that all sessions have finished
+		assert!(ml.nodes.values().all(|n| n.session.is_finished()));
+		assert_eq!(ml.nodes[&master_node_id].session.wait(), Ok(()));
+	}
+
+	#[test]
+	fn removed_master_completes_after_own_share_change_sessions_are_completed() {
+		// initial 2-of-3 session
+		let gml = generate_key(1, generate_nodes_ids(3));
+
+		// master is selected as share change session master => it runs share change session itself
+		let master_node_id = select_share_change_master(&SessionId::default(), &gml.nodes.keys().cloned().collect(), None).unwrap();
+
+		// remove master node && insert 1 node
+		let nodes_to_remove: BTreeSet<_> = vec![master_node_id.clone()].into_iter().collect();
+		let nodes_to_add: BTreeSet<_> = (0..1).map(|_| Random.generate().unwrap().public().clone()).collect();
+		let mut ml = MessageLoop::new(&gml, master_node_id, None, nodes_to_add.clone(), nodes_to_remove.clone(), BTreeSet::new());
+		let new_nodes_set = ml.nodes.keys().cloned().filter(|n| !nodes_to_remove.contains(n)).collect();
+		ml.nodes[&master_node_id].session.initialize(new_nodes_set, ml.all_set_signature.clone(), ml.new_set_signature.clone()).unwrap();
+		ml.run();
+
+		// check that secret is recoverable from remaining nodes (including added node)
+		check_secret_is_preserved(ml.original_key_pair.clone(), ml.nodes.iter()
+			.filter(|&(k, _)| !nodes_to_remove.contains(k))
+			.map(|(k, v)| (k.clone(), v.key_storage.clone()))
+			.collect());
+
+		// check that ex-master holds no key share
+		assert!(ml.nodes[&master_node_id].key_storage.get(&SessionId::default()).unwrap().is_none());
+
+		// check that all sessions have finished
+		assert!(ml.nodes.values().all(|n| n.session.is_finished()));
+		assert_eq!(ml.nodes[&master_node_id].session.wait(), Ok(()));
+	}
+
 	#[test]
 	fn node_removed_using_servers_set_change() {
 		// initial 2-of-3 session
@@ -1298,6 +3442,32 @@ pub mod tests {
 		assert!(ml.nodes.values().all(|n| n.session.is_finished()));
 	}
 
+	#[test]
+	fn more_nodes_removed_than_added_using_servers_set_change() {
+		// initial 2-of-4 session
+		let gml = generate_key(1, generate_nodes_ids(4));
+		let master_node_id = gml.nodes.keys().cloned().nth(0).unwrap();
+
+		// remove 2 nodes && add nothing so that session becames 2-of-2
+		let nodes_to_remove: BTreeSet<_> = gml.nodes.keys().cloned().skip(2).collect();
+		let new_nodes_set: BTreeSet<_> = gml.nodes.keys().cloned().filter(|n| !nodes_to_remove.contains(&n)).collect();
+		let mut ml = MessageLoop::new(&gml, master_node_id, None, BTreeSet::new(), nodes_to_remove.clone(), BTreeSet::new());
+		ml.nodes[&master_node_id].session.initialize(new_nodes_set, ml.all_set_signature.clone(), ml.new_set_signature.clone()).unwrap();
+		ml.run();
+
+		// check that secret is still recoverable by remaining nodes
+		check_secret_is_preserved(ml.original_key_pair.clone(), ml.nodes.iter()
+			.filter(|&(k, _)| !nodes_to_remove.contains(k))
+			.map(|(k, v)| (k.clone(), v.key_storage.clone()))
+			.collect());
+
+		// check that all removed nodes do not own key share
+		assert!(ml.nodes.iter().filter(|&(k, _)| nodes_to_remove.contains(k)).all(|(_, v)| v.key_storage.get(&SessionId::default()).unwrap().is_none()));
+
+		// check that all sessions have finished
+		assert!(ml.nodes.values().all(|n| n.session.is_finished()));
+	}
+
 	#[test]
 	fn isolated_node_removed_using_servers_set_change() {
 		// initial 2-of-3 session
@@ -1387,7 +3557,7 @@ pub mod tests {
 		// insert 1 node so that it becames 2-of-3 session
 		let nodes_to_add: BTreeSet<_> = (0..1).map(|_| Random.generate().unwrap().public().clone()).collect();
 		let mut ml = MessageLoop::new(&gml, master_node_id, None, nodes_to_add.clone(), BTreeSet::new(), BTreeSet::new());
-		ml.nodes[&master_node_id].session.initialize(ml.nodes.keys().cloned().collect(), ml.all_set_signature.clone(), ml.new_set_signature.clone()).unwrap();
+		ml.initialize().unwrap();
 		ml.run();
 
 		// now let's say new node has lost its db and we're trying to join it again
@@ -1395,7 +3565,7 @@ pub mod tests {
 
 		// this time old nodes have version, where new node is mentioned, but it doesn't report it when negotiating
 		let mut ml = MessageLoop::new(&gml, master_node_id, None, nodes_to_add, BTreeSet::new(), BTreeSet::new());
-		ml.nodes[&master_node_id].session.initialize(ml.nodes.keys().cloned().collect(), ml.all_set_signature.clone(), ml.new_set_signature.clone()).unwrap();
+		ml.initialize().unwrap();
 		ml.run();
 
 		// try to recover secret for every possible combination of nodes && check that secret is the same
@@ -1404,4 +3574,151 @@ pub mod tests {
 		// check that all sessions have finished
 		assert!(ml.nodes.values().all(|n| n.session.is_finished()));
 	}
+
+	fn adversarial_messages(key_id: SessionId, nodes: &BTreeSet<NodeId>) -> Vec<ServersSetChangeMessage> {
+		let session: SessionId = Default::default();
+		let random_node = || Random.generate().unwrap().public().clone();
+		let random_secret = || Random.generate().unwrap().secret().clone();
+		let foreign_nodes: BTreeSet<_> = nodes.iter().cloned().chain((0..2).map(|_| random_node())).collect();
+		vec![
+			ServersSetChangeMessage::UnknownSessionsRequest(UnknownSessionsRequest {
+				session: session.clone().into(),
+				session_nonce: 1,
+			}),
+			ServersSetChangeMessage::UnknownSessions(UnknownSessions {
+				session: session.clone().into(),
+				session_nonce: 1,
+				unknown_sessions: vec![key_id.clone().into(), SessionId::default().into()].into_iter().collect(),
+			}),
+			ServersSetChangeMessage::ShareChangeKeyVersionNegotiation(ShareChangeKeyVersionNegotiation {
+				session: session.clone().into(),
+				session_nonce: 1,
+				message: KeyVersionNegotiationMessage::RequestKeyVersions(RequestKeyVersions {
+					session: key_id.clone().into(),
+					sub_session: random_secret().into(),
+					session_nonce: 1,
+				}),
+			}),
+			ServersSetChangeMessage::ShareChangeKeyVersionNegotiation(ShareChangeKeyVersionNegotiation {
+				session: session.clone().into(),
+				session_nonce: 1,
+				message: KeyVersionNegotiationMessage::KeyVersions(KeyVersions {
+					session: key_id.clone().into(),
+					sub_session: random_secret().into(),
+					session_nonce: 1,
+					threshold: Some(::std::usize::MAX),
+					versions: vec![H256::random().into()],
+				}),
+			}),
+			ServersSetChangeMessage::InitializeShareChangeSession(InitializeShareChangeSession {
+				session: session.clone().into(),
+				session_nonce: 1,
+				key_id: key_id.clone().into(),
+				version: H256::random().into(),
+				version_holders: BTreeSet::new(),
+				master_node_id: random_node().into(),
+				consensus_group: BTreeSet::new(),
+				new_nodes_map: BTreeMap::new(),
+			}),
+			ServersSetChangeMessage::InitializeShareChangeSession(InitializeShareChangeSession {
+				session: session.clone().into(),
+				session_nonce: 1,
+				key_id: key_id.clone().into(),
+				version: H256::random().into(),
+				version_holders: foreign_nodes.iter().cloned().map(Into::into).collect(),
+				master_node_id: random_node().into(),
+				consensus_group: foreign_nodes.iter().cloned().map(Into::into).collect(),
+				new_nodes_map: foreign_nodes.iter().map(|n| (n.clone().into(), Some(random_secret().into()))).collect(),
+			}),
+			ServersSetChangeMessage::ConfirmShareChangeSessionInitialization(ConfirmShareChangeSessionInitialization {
+				session: session.clone().into(),
+				session_nonce: 1,
+				key_id: key_id.clone().into(),
+			}),
+			ServersSetChangeMessage::ServersSetChangeDelegate(ServersSetChangeDelegate {
+				session: session.clone().into(),
+				session_nonce: 1,
+				key_id: key_id.clone().into(),
+			}),
+			ServersSetChangeMessage::ServersSetChangeDelegateResponse(ServersSetChangeDelegateResponse {
+				session: session.clone().into(),
+				session_nonce: 1,
+				key_id: key_id.clone().into(),
+			}),
+			ServersSetChangeMessage::ServersSetChangeShareAddMessage(ServersSetChangeShareAddMessage {
+				session: session.clone().into(),
+				session_nonce: 1,
+				message: ShareAddMessage::KeyShareCommon(KeyShareCommon {
+					session: key_id.clone().into(),
+					session_nonce: 1,
+					threshold: ::std::usize::MAX,
+					author: Address::random().into(),
+					joint_public: random_node().into(),
+					common_point: None,
+					encrypted_point: None,
+					id_numbers: foreign_nodes.iter().map(|n| (n.clone().into(), random_secret().into())).collect(),
+				}),
+			}),
+			ServersSetChangeMessage::ServersSetChangeShareAddMessage(ServersSetChangeShareAddMessage {
+				session: session.clone().into(),
+				session_nonce: 1,
+				message: ShareAddMessage::ShareAddError(ShareAddError {
+					session: key_id.clone().into(),
+					session_nonce: 1,
+					error: Error::InvalidMessage,
+				}),
+			}),
+			ServersSetChangeMessage::ServersSetChangeCompleted(ServersSetChangeCompleted {
+				session: session.clone().into(),
+				session_nonce: 1,
+				is_dry_run: false,
+			}),
+		]
+	}
+
+	fn feed_adversarial_messages(ml: &MessageLoop) {
+		let nodes: BTreeSet<_> = ml.nodes.keys().cloned().collect();
+		let senders: Vec<_> = nodes.iter().cloned().chain(::std::iter::once(Random.generate().unwrap().public().clone())).collect();
+		for key_id in vec![SessionId::default(), H256::random()] {
+			for message in adversarial_messages(key_id, &nodes) {
+				for node in ml.nodes.values() {
+					for sender in &senders {
+						// any result is fine here, as long as the session doesn't panic
+						let _ = node.session.process_message(sender, &message);
+					}
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn adversarial_messages_never_panic() {
+		// initial 2-of-3 session
+		let gml = generate_key(1, generate_nodes_ids(3));
+		let master_node_id = gml.nodes.keys().cloned().nth(0).unwrap();
+		let nodes_to_add: BTreeSet<_> = (0..1).map(|_| Random.generate().unwrap().public().clone()).collect();
+
+		// before session is initialized
+		let ml = MessageLoop::new(&gml, master_node_id, None, nodes_to_add.clone(), BTreeSet::new(), BTreeSet::new());
+		feed_adversarial_messages(&ml);
+
+		// while session is running
+		let mut ml = MessageLoop::new(&gml, master_node_id, None, nodes_to_add.clone(), BTreeSet::new(), BTreeSet::new());
+		ml.initialize().unwrap();
+		for _ in 0..4 {
+			if let Some(message) = ml.take_message() {
+				ml.process_message(message).unwrap();
+			}
+		}
+		feed_adversarial_messages(&ml);
+		while let Some(message) = ml.take_message() {
+			let _ = ml.process_message(message);
+		}
+
+		// after session is completed
+		let mut ml = MessageLoop::new(&gml, master_node_id, None, nodes_to_add, BTreeSet::new(), BTreeSet::new());
+		ml.initialize().unwrap();
+		ml.run();
+		feed_adversarial_messages(&ml);
+	}
 }
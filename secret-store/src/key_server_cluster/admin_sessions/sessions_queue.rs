@@ -15,34 +15,91 @@
 // along with Parity Ethereum.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::sync::Arc;
-use std::collections::{VecDeque, BTreeSet};
+use std::cmp::Reverse;
+use std::collections::{VecDeque, BTreeSet, BTreeMap};
 use key_server_cluster::{Error, SessionId, KeyStorage};
 
-/// Queue of share change sessions.
+/// Queue of share change sessions. Queue is only created on master node, so other nodes never compute dispatch order.
+/// For the same keys set && priorities, keys are always dispatched in the same order.
 pub struct SessionsQueue {
+	/// Key storage.
+	key_storage: Arc<KeyStorage>,
 	/// Sessions, known on this node.
 	known_sessions: VecDeque<SessionId>,
 	/// Unknown sessions.
 	unknown_sessions: VecDeque<SessionId>,
+	/// Keys priorities (keys with greater priority are dispatched first, keys are 0-priority by default).
+	priorities: BTreeMap<SessionId, u8>,
+	/// Number of known sessions, which have been skipped because key has been removed from the storage.
+	skipped: usize,
 }
 
 impl SessionsQueue {
 	/// Create new sessions queue.
-	pub fn new(key_storage: &Arc<KeyStorage>, unknown_sessions: BTreeSet<SessionId>) -> Self {
+	pub fn new(key_storage: &Arc<KeyStorage>, unknown_sessions: BTreeSet<SessionId>, priorities: BTreeMap<SessionId, u8>) -> Self {
 		// TODO [Opt]:
 		// 1) known sessions - change to iter
 		// 2) unknown sesions - request chunk-by-chunk
+
+		// known sessions are ordered by priority, then by number of key share owners (fragile keys first), then by key id
+		// (key storage iteration order is unspecified => key id makes the order deterministic)
+		let mut known_sessions: Vec<_> = key_storage.iter()
+			.map(|(k, v)| {
+				let owners_count = v.last_version().map(|v| v.id_numbers.len()).unwrap_or_default();
+				(k, owners_count)
+			})
+			.collect();
+		known_sessions.sort_by_key(|&(ref k, owners_count)| (Reverse(priority(&priorities, k)), owners_count, k.clone()));
+
+		// unknown sessions are ordered by priority, then by key id
+		let mut unknown_sessions: Vec<_> = unknown_sessions.into_iter().collect();
+		unknown_sessions.sort_by_key(|k| (Reverse(priority(&priorities, k)), k.clone()));
+
 		SessionsQueue {
-			known_sessions: key_storage.iter().map(|(k, _)| k).collect(),
+			key_storage: key_storage.clone(),
+			known_sessions: known_sessions.into_iter().map(|(k, _)| k).collect(),
 			unknown_sessions: unknown_sessions.into_iter().collect(),
+			priorities: priorities,
+			skipped: 0,
 		}
 	}
+
+	/// Get number of known sessions, which have been skipped because key has been removed from the storage.
+	pub fn skipped(&self) -> usize {
+		self.skipped
+	}
+
+	/// Only keep sessions of given keys in the queue.
+	pub fn retain(&mut self, keys: &BTreeSet<SessionId>) {
+		self.known_sessions.retain(|k| keys.contains(k));
+		self.unknown_sessions.retain(|k| keys.contains(k));
+	}
 }
 
 impl Iterator for SessionsQueue {
 	type Item = Result<SessionId, Error>;
 
 	fn next(&mut self) -> Option<Self::Item> {
+		// key could be removed from the storage after queue has been created => skip it
+		while let Some(known_session) = self.known_sessions.front().cloned() {
+			if self.key_storage.contains(&known_session) {
+				break;
+			}
+
+			warn!(target: "secretstore_net", "skipping share change session for key {}: key has been removed from the storage", known_session);
+			self.known_sessions.pop_front();
+			self.skipped += 1;
+		}
+
+		// unknown session is dispatched before known one only if it has greater priority
+		let known_priority = self.known_sessions.front().map(|k| priority(&self.priorities, k));
+		let unknown_priority = self.unknown_sessions.front().map(|k| priority(&self.priorities, k));
+		match (known_priority, unknown_priority) {
+			(Some(known_priority), Some(unknown_priority)) if unknown_priority > known_priority =>
+				return self.unknown_sessions.pop_front().map(Ok),
+			_ => (),
+		}
+
 		if let Some(known_session) = self.known_sessions.pop_front() {
 			return Some(Ok(known_session));
 		}
@@ -53,4 +110,101 @@ impl Iterator for SessionsQueue {
 
 		None
 	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let len = self.known_sessions.len() + self.unknown_sessions.len();
+		(len, Some(len))
+	}
+}
+
+impl ExactSizeIterator for SessionsQueue {}
+
+/// Get priority of the key.
+fn priority(priorities: &BTreeMap<SessionId, u8>, key_id: &SessionId) -> u8 {
+	priorities.get(key_id).cloned().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use std::collections::{BTreeSet, BTreeMap};
+	use ethkey::{Random, Generator};
+	use key_server_cluster::{SessionId, KeyStorage, DummyKeyStorage, DocumentKeyShare, DocumentKeyShareVersion};
+	use super::SessionsQueue;
+
+	fn key_share(owners_count: usize) -> DocumentKeyShare {
+		DocumentKeyShare {
+			author: Default::default(),
+			threshold: 0,
+			public: Default::default(),
+			common_point: None,
+			encrypted_point: None,
+			versions: vec![DocumentKeyShareVersion::new(
+				(0..owners_count).map(|_| (Random.generate().unwrap().public().clone(), Random.generate().unwrap().secret().clone())).collect(),
+				Random.generate().unwrap().secret().clone(),
+			)],
+		}
+	}
+
+	#[test]
+	fn sessions_queue_dispatches_high_priority_keys_first() {
+		let key_storage = Arc::new(DummyKeyStorage::default());
+		key_storage.insert(SessionId::from(1), key_share(2)).unwrap();
+		key_storage.insert(SessionId::from(2), key_share(3)).unwrap();
+		key_storage.insert(SessionId::from(3), key_share(4)).unwrap();
+		let unknown_sessions: BTreeSet<_> = vec![SessionId::from(4), SessionId::from(5)].into_iter().collect();
+		let priorities: BTreeMap<_, _> = vec![(SessionId::from(3), 10), (SessionId::from(5), 5)].into_iter().collect();
+
+		let key_storage: Arc<KeyStorage> = key_storage;
+		let queue = SessionsQueue::new(&key_storage, unknown_sessions, priorities);
+		assert_eq!(queue.map(Result::unwrap).collect::<Vec<_>>(), vec![
+			// priority 10
+			SessionId::from(3),
+			// priority 5
+			SessionId::from(5),
+			// priority 0, 2 owners
+			SessionId::from(1),
+			// priority 0, 3 owners
+			SessionId::from(2),
+			// priority 0, unknown
+			SessionId::from(4),
+		]);
+	}
+
+	#[test]
+	fn sessions_queue_order_is_deterministic() {
+		let key_storage = Arc::new(DummyKeyStorage::default());
+		for key_id in (0..32u64).rev() {
+			key_storage.insert(SessionId::from(key_id), key_share(2)).unwrap();
+		}
+		let unknown_sessions: BTreeSet<_> = (32..64u64).map(SessionId::from).collect();
+
+		let key_storage: Arc<KeyStorage> = key_storage;
+		let first_run: Vec<_> = SessionsQueue::new(&key_storage, unknown_sessions.clone(), Default::default())
+			.map(Result::unwrap).collect();
+		let second_run: Vec<_> = SessionsQueue::new(&key_storage, unknown_sessions, Default::default())
+			.map(Result::unwrap).collect();
+
+		// keys with the same priority && owners count are dispatched in key id order
+		assert_eq!(first_run, (0..64u64).map(SessionId::from).collect::<Vec<_>>());
+		assert_eq!(first_run, second_run);
+	}
+
+	#[test]
+	fn sessions_queue_skips_keys_removed_from_storage() {
+		let key_storage = Arc::new(DummyKeyStorage::default());
+		key_storage.insert(SessionId::from(1), key_share(2)).unwrap();
+		key_storage.insert(SessionId::from(2), key_share(3)).unwrap();
+		key_storage.insert(SessionId::from(3), key_share(4)).unwrap();
+		let unknown_sessions: BTreeSet<_> = vec![SessionId::from(4)].into_iter().collect();
+
+		let key_storage: Arc<KeyStorage> = key_storage;
+		let mut queue = SessionsQueue::new(&key_storage, unknown_sessions, Default::default());
+		assert_eq!(queue.next().unwrap().unwrap(), SessionId::from(1));
+
+		// remove key after queue has been created
+		key_storage.remove(&SessionId::from(2)).unwrap();
+		assert_eq!(queue.by_ref().map(Result::unwrap).collect::<Vec<_>>(), vec![SessionId::from(3), SessionId::from(4)]);
+		assert_eq!(queue.skipped(), 1);
+	}
 }
@@ -249,7 +249,10 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 
 	/// Initialize share add session on master node.
 	pub fn initialize(&self, version: Option<H256>, new_nodes_set: Option<BTreeSet<NodeId>>, old_set_signature: Option<Signature>, new_set_signature: Option<Signature>) -> Result<(), Error> {
-		debug_assert_eq!(self.core.meta.self_node_id, self.core.meta.master_node_id);
+		// session could only be initialized on master node
+		if self.core.meta.self_node_id != self.core.meta.master_node_id {
+			return Err(Error::InvalidStateForRequest);
+		}
 
 		let mut data = self.data.lock();
 
@@ -311,7 +314,7 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 		// create && initialize consensus session
 		let mut consensus_session = ConsensusSession::new(ConsensusSessionParams {
 			meta: self.core.meta.clone().into_consensus_meta(new_nodes_map.len())?,
-			consensus_executor: ServersSetChangeAccessJob::new_on_master(admin_public,
+			consensus_executor: ServersSetChangeAccessJob::new_on_master(::std::iter::once(admin_public).collect(),
 				old_nodes_set.clone(),
 				new_nodes_map.keys().cloned().collect(),
 				old_set_signature,
@@ -330,9 +333,14 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 		Ok(())
 	}
 
+	/// Get session result. Returns None if session is not yet completed.
+	pub fn result(&self) -> Option<Result<(), Error>> {
+		self.data.lock().result.clone()
+	}
+
 	/// Process single message.
 	pub fn process_message(&self, sender: &NodeId, message: &ShareAddMessage) -> Result<(), Error> {
-		if self.core.nonce != message.session_nonce() {
+		if self.core.meta.id != *message.session_id() || self.core.nonce != message.session_nonce() {
 			return Err(Error::ReplayProtection);
 		}
 
@@ -353,7 +361,9 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 	/// When consensus-related message is received.
 	pub fn on_consensus_message(&self, sender: &NodeId, message: &ShareAddConsensusMessage) -> Result<(), Error> {
 		debug_assert!(self.core.meta.id == *message.session);
-		debug_assert!(sender != &self.core.meta.self_node_id);
+		if sender == &self.core.meta.self_node_id {
+			return Err(Error::InvalidMessage);
+		}
 
 		// start slave consensus session if needed
 		let mut data = self.data.lock();
@@ -363,7 +373,7 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 					let admin_public = self.core.admin_public.as_ref().cloned().ok_or(Error::ConsensusUnreachable)?;
 					data.consensus_session = Some(ConsensusSession::new(ConsensusSessionParams {
 						meta: self.core.meta.clone().into_consensus_meta(message.new_nodes_map.len())?,
-						consensus_executor: ServersSetChangeAccessJob::new_on_slave(admin_public),
+						consensus_executor: ServersSetChangeAccessJob::new_on_slave(::std::iter::once(admin_public).collect()),
 						consensus_transport: self.core.transport.clone(),
 					})?);
 				},
@@ -437,7 +447,9 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 	/// When common key share data is received by new node.
 	pub fn on_common_key_share_data(&self, sender: &NodeId, message: &KeyShareCommon) -> Result<(), Error> {
 		debug_assert!(self.core.meta.id == *message.session);
-		debug_assert!(sender != &self.core.meta.self_node_id);
+		if sender == &self.core.meta.self_node_id {
+			return Err(Error::InvalidMessage);
+		}
 
 		// only master can send this message
 		if sender != &self.core.meta.master_node_id {
@@ -465,6 +477,13 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 			}
 		}
 
+		// consensus group consists of threshold + 1 nodes => threshold must match its size
+		if let Some(ref secret_subshares) = data.secret_subshares {
+			if secret_subshares.len().checked_sub(1) != Some(message.threshold) {
+				return Err(Error::InvalidMessage);
+			}
+		}
+
 		// update data
 		data.state = SessionState::WaitingForKeysDissemination;
 		data.new_key_share = Some(NewKeyShare {
@@ -503,7 +522,9 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 	/// When keys dissemination message is received.
 	pub fn on_new_keys_dissemination(&self, sender: &NodeId, message: &NewKeysDissemination) -> Result<(), Error> {
 		debug_assert!(self.core.meta.id == *message.session);
-		debug_assert!(sender != &self.core.meta.self_node_id);
+		if sender == &self.core.meta.self_node_id {
+			return Err(Error::InvalidMessage);
+		}
 
 		let mut data = self.data.lock();
 
@@ -700,10 +721,11 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 				.expect("computation occurs after receiving key share threshold if not having one already; qed")
 				.threshold);
 
-		let explanation = "id_numbers are checked to have Some value for every consensus group node when consensus is establishe; qed";
-		let sender_id_number = id_numbers[sender].as_ref().expect(explanation);
-		let other_id_numbers = secret_subshares.keys().filter(|k| *k != sender).map(|n| id_numbers[n].as_ref().expect(explanation));
-		math::compute_secret_subshare(threshold, secret_value, sender_id_number, other_id_numbers)
+		// id numbers of consensus group nodes are (partially) received from master => do not trust them to be filled
+		let id_number = |n: &NodeId| id_numbers.get(n).and_then(|id| id.as_ref()).ok_or(Error::InvalidMessage);
+		let sender_id_number = id_number(sender)?;
+		let other_id_numbers = secret_subshares.keys().filter(|k| *k != sender).map(|n| id_number(n)).collect::<Result<Vec<_>, _>>()?;
+		math::compute_secret_subshare(threshold, secret_value, sender_id_number, other_id_numbers.into_iter())
 	}
 
 	/// Complete session.
@@ -721,9 +743,11 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 		let secret_share = math::compute_secret_share(secret_subshares.values().map(|ss| ss.as_ref()
 			.expect("complete_session is only called when subshares from all nodes are received; qed")))?;
 
-		let refreshed_key_version = DocumentKeyShareVersion::new(id_numbers.clone().into_iter().map(|(k, v)| (k.clone(),
-			v.expect("id_numbers are checked to have Some value for every consensus group node when consensus is establishe; qed"))).collect(),
+		let refreshed_key_version = DocumentKeyShareVersion::new(id_numbers.clone().into_iter()
+			.map(|(k, v)| v.map(|v| (k, v)).ok_or(Error::InvalidMessage))
+			.collect::<Result<_, _>>()?,
 			secret_share);
+		check_id_numbers_are_unique(&refreshed_key_version.id_numbers)?;
 		let mut refreshed_key_share = core.key_share.as_ref().cloned().unwrap_or_else(|| {
 			let new_key_share = data.new_key_share.as_ref()
 				.expect("this is new node; on new nodes this field is filled before KRD; session is completed after KRD; qed");
@@ -771,7 +795,7 @@ impl<T> ClusterSession for SessionImpl<T> where T: SessionTransport {
 	}
 
 	fn on_session_timeout(&self) {
-		self.on_session_error(&self.core.meta.self_node_id, Error::NodeDisconnected)
+		self.on_session_error(&self.core.meta.self_node_id, Error::Timeout)
 	}
 
 	fn on_node_timeout(&self, node: &NodeId) {
@@ -805,7 +829,7 @@ impl<T> ClusterSession for SessionImpl<T> where T: SessionTransport {
 	fn on_message(&self, sender: &NodeId, message: &Message) -> Result<(), Error> {
 		match *message {
 			Message::ShareAdd(ref message) => self.process_message(sender, message),
-			_ => unreachable!("cluster checks message to be correct before passing; qed"),
+			_ => Err(Error::InvalidMessage),
 		}
 	}
 }
@@ -879,6 +903,16 @@ impl SessionTransport for IsolatedSessionTransport {
 	}
 }
 
+/// Check that every key share owner has unique id number (otherwise key is irrecoverable).
+fn check_id_numbers_are_unique(id_numbers: &BTreeMap<NodeId, Secret>) -> Result<(), Error> {
+	let unique_id_numbers: BTreeSet<H256> = id_numbers.values().map(|id_number| (**id_number).clone()).collect();
+	if unique_id_numbers.len() != id_numbers.len() {
+		return Err(Error::InvalidNodesConfiguration);
+	}
+
+	Ok(())
+}
+
 #[cfg(test)]
 pub mod tests {
 	use std::sync::Arc;
@@ -891,11 +925,11 @@ pub mod tests {
 	use key_server_cluster::cluster_sessions::ClusterSession;
 	use key_server_cluster::generation_session::tests::{Node as GenerationNode, generate_nodes_ids};
 	use key_server_cluster::math;
-	use key_server_cluster::message::Message;
+	use key_server_cluster::message::{Message, ShareAddMessage};
 	use key_server_cluster::servers_set_change_session::tests::generate_key;
 	use key_server_cluster::jobs::servers_set_change_access_job::ordered_nodes_hash;
 	use key_server_cluster::admin_sessions::ShareChangeSessionMeta;
-	use super::{SessionImpl, SessionParams, IsolatedSessionTransport};
+	use super::{SessionImpl, SessionParams, IsolatedSessionTransport, check_id_numbers_are_unique};
 
 	struct Node {
 		pub cluster: Arc<DummyCluster>,
@@ -1127,6 +1161,19 @@ pub mod tests {
 		).unwrap_err(), Error::ConsensusUnreachable);
 	}
 
+	#[test]
+	fn node_add_fails_if_initialized_on_slave_node() {
+		let old_nodes_set = generate_nodes_ids(3);
+		let master_node_id = old_nodes_set.iter().cloned().nth(0).unwrap();
+		let slave_node_id = old_nodes_set.iter().cloned().nth(1).unwrap();
+		let new_nodes_set: BTreeSet<_> = old_nodes_set.clone().into_iter().chain(generate_nodes_ids(1)).collect();
+		let ml = MessageLoop::new(1, master_node_id.clone(), old_nodes_set, new_nodes_set.clone());
+		assert_eq!(ml.nodes[&slave_node_id].session.initialize(Some(ml.version), Some(new_nodes_set),
+			Some(ml.old_set_signature.clone()),
+			Some(ml.new_set_signature.clone())
+		).unwrap_err(), Error::InvalidStateForRequest);
+	}
+
 	#[test]
 	fn node_add_fails_if_started_on_adding_node() {
 		let old_nodes_set = generate_nodes_ids(3);
@@ -1189,6 +1236,38 @@ pub mod tests {
 		}
 	}
 
+	#[test]
+	fn key_share_common_with_wrong_threshold_is_rejected() {
+		let old_nodes_set = generate_nodes_ids(3);
+		let nodes_to_add_set = generate_nodes_ids(1);
+		let new_nodes_set: BTreeSet<_> = old_nodes_set.clone().into_iter().chain(nodes_to_add_set.clone()).collect();
+		let master_node_id = old_nodes_set.iter().cloned().nth(0).unwrap();
+		let mut ml = MessageLoop::new(1, master_node_id.clone(), old_nodes_set, new_nodes_set.clone());
+		ml.nodes[&master_node_id].session.initialize(Some(ml.version), Some(new_nodes_set),
+			Some(ml.old_set_signature.clone()),
+			Some(ml.new_set_signature.clone())).unwrap();
+
+		// master sends wrong threshold to the new node
+		loop {
+			let (from, to, mut message) = ml.take_message().unwrap();
+			let is_tampered = match message {
+				Message::ShareAdd(ShareAddMessage::KeyShareCommon(ref mut message)) => {
+					message.threshold = message.threshold + 1;
+					true
+				},
+				_ => false,
+			};
+
+			if is_tampered {
+				assert!(nodes_to_add_set.contains(&to));
+				assert_eq!(ml.process_message((from, to, message)), Err(Error::InvalidMessage));
+				break;
+			}
+
+			ml.process_message((from, to, message)).unwrap();
+		}
+	}
+
 	#[test]
 	fn nodes_added_using_share_add_with_isolated_nodes() {
 		let (n, nodes_to_add) = (3, 3);
@@ -1324,4 +1403,18 @@ pub mod tests {
 			Some(ml.old_set_signature.clone()),
 			Some(ml.new_set_signature.clone())).map(|_| ()), Err(Error::ConsensusUnreachable));
 	}
+
+	#[test]
+	fn duplicate_id_numbers_are_rejected() {
+		let id_number = math::generate_random_scalar().unwrap();
+		let unique_id_numbers: BTreeMap<_, _> = generate_nodes_ids(3).into_iter()
+			.map(|n| (n, math::generate_random_scalar().unwrap()))
+			.collect();
+		let duplicate_id_numbers: BTreeMap<_, _> = generate_nodes_ids(3).into_iter()
+			.map(|n| (n, id_number.clone()))
+			.collect();
+
+		assert_eq!(check_id_numbers_are_unique(&unique_id_numbers), Ok(()));
+		assert_eq!(check_id_numbers_are_unique(&duplicate_id_numbers), Err(Error::InvalidNodesConfiguration));
+	}
 }
@@ -56,12 +56,16 @@ pub struct ShareChangeSession {
 	new_nodes_map: Option<BTreeMap<NodeId, Option<Secret>>>,
 	/// Share add session.
 	share_add_session: Option<ShareAddSessionImpl<ShareChangeTransport>>,
+	/// All nodes, participating in this session.
+	nodes: BTreeSet<NodeId>,
+	/// Session plan (it is consumed when share add session is created => this copy is only used for inspection).
+	plan: ShareChangeSessionPlan,
 	/// Is finished.
 	is_finished: bool,
 }
 
 /// Share change session plan.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ShareChangeSessionPlan {
 	/// Key version that plan is valid for.
 	pub key_version: H256,
@@ -104,6 +108,7 @@ impl ShareChangeSession {
 	/// Create new share change session.
 	pub fn new(params: ShareChangeSessionParams) -> Result<Self, Error> {
 		// we can't create sessions right now, because key share is read when session is created, but it can change in previous session
+		let plan = params.plan.clone();
 		let key_version = params.plan.key_version;
 		let consensus_group = if !params.plan.consensus_group.is_empty() { Some(params.plan.consensus_group) } else { None };
 		let version_holders = if !params.plan.version_holders.is_empty() { Some(params.plan.version_holders) } else { None };
@@ -111,6 +116,11 @@ impl ShareChangeSession {
 		debug_assert!(new_nodes_map.is_some());
 
 		let is_finished = new_nodes_map.is_none();
+		let nodes = consensus_group.iter().flat_map(|g| g.iter())
+			.chain(new_nodes_map.iter().flat_map(|m| m.keys()))
+			.chain(::std::iter::once(&params.meta.master_node_id))
+			.cloned()
+			.collect();
 		Ok(ShareChangeSession {
 			session_id: params.session_id,
 			nonce: params.nonce,
@@ -122,6 +132,8 @@ impl ShareChangeSession {
 			consensus_group: consensus_group,
 			new_nodes_map: new_nodes_map,
 			share_add_session: None,
+			nodes: nodes,
+			plan: plan,
 			is_finished: is_finished,
 		})
 	}
@@ -136,6 +148,16 @@ impl ShareChangeSession {
 		self.meta.self_node_id == self.meta.master_node_id
 	}
 
+	/// Get session plan.
+	pub fn plan(&self) -> &ShareChangeSessionPlan {
+		&self.plan
+	}
+
+	/// Is given node required to complete this session?
+	pub fn is_node_required(&self, node: &NodeId) -> bool {
+		!self.is_finished && self.nodes.contains(node)
+	}
+
 	/// Initialize session (on master node).
 	pub fn initialize(&mut self) -> Result<(), Error> {
 		self.proceed_to_next_state()
@@ -155,6 +177,11 @@ impl ShareChangeSession {
 			})
 			.unwrap_or(Err(Error::InvalidMessage))?;
 		if change_state_needed {
+			// share add session could also finish because of error on other node
+			if let Some(Err(error)) = self.share_add_session.as_ref().and_then(|share_add_session| share_add_session.result()) {
+				return Err(error);
+			}
+
 			self.proceed_to_next_state()?;
 		}
 
@@ -242,7 +269,7 @@ impl ShareAddSessionTransport for ShareChangeTransport {
 }
 
 /// Prepare share change plan for moving from old `old_key_version_owners` to `new_nodes_set`.
-pub fn prepare_share_change_session_plan(cluster_nodes: &BTreeSet<NodeId>, threshold: usize, key_id: &ServerKeyId, key_version: H256, master: &NodeId, old_key_version_owners: &BTreeSet<NodeId>, new_nodes_set: &BTreeSet<NodeId>) -> Result<ShareChangeSessionPlan, Error> {
+pub fn prepare_share_change_session_plan(cluster_nodes: &BTreeSet<NodeId>, readonly_nodes: &BTreeSet<NodeId>, threshold: usize, key_id: &ServerKeyId, key_version: H256, master: &NodeId, old_key_version_owners: &BTreeSet<NodeId>, new_nodes_set: &BTreeSet<NodeId>) -> Result<ShareChangeSessionPlan, Error> {
 	// we can't do anything if there are no enought shares
 	if old_key_version_owners.len() < threshold + 1 {
 		warn!("cannot add shares to key {} with threshold {}: only {} shares owners are available",
@@ -263,8 +290,9 @@ pub fn prepare_share_change_session_plan(cluster_nodes: &BTreeSet<NodeId>, thres
 
 	// make new nodes map, so that:
 	// all non-isolated old nodes will have their id number preserved
-	// all new nodes will have new id number
+	// all new nodes (except readonly nodes) will have new id number
 	let mut new_nodes_map = new_nodes_set.difference(&old_key_version_owners)
+		.filter(|n| !readonly_nodes.contains(n))
 		.map(|n| math::generate_random_scalar().map(|id| (n.clone(), Some(id))))
 		.collect::<Result<BTreeMap<_, _>, _>>()?;
 	if !new_nodes_map.is_empty() {
@@ -302,6 +330,7 @@ impl ShareChangeSessionPlan {
 
 #[cfg(test)]
 mod tests {
+	use std::collections::BTreeSet;
 	use key_server_cluster::math;
 	use super::prepare_share_change_session_plan;
 
@@ -311,7 +340,7 @@ mod tests {
 		let master = cluster_nodes[0].clone();
 		let old_key_version_owners = cluster_nodes.iter().cloned().collect();
 		let new_nodes_set = cluster_nodes.iter().cloned().collect();
-		let plan = prepare_share_change_session_plan(&cluster_nodes.iter().cloned().collect(),
+		let plan = prepare_share_change_session_plan(&cluster_nodes.iter().cloned().collect(), &BTreeSet::new(),
 			1, &Default::default(), Default::default(), &master, &old_key_version_owners, &new_nodes_set).unwrap();
 
 		assert!(plan.is_empty());
@@ -323,11 +352,25 @@ mod tests {
 		let master = cluster_nodes[0].clone();
 		let old_key_version_owners = cluster_nodes[0..2].iter().cloned().collect();
 		let new_nodes_set = cluster_nodes.iter().cloned().collect();
-		let plan = prepare_share_change_session_plan(&cluster_nodes.iter().cloned().collect(),
+		let plan = prepare_share_change_session_plan(&cluster_nodes.iter().cloned().collect(), &BTreeSet::new(),
 			1, &Default::default(), Default::default(), &master, &old_key_version_owners, &new_nodes_set).unwrap();
 
 		assert!(!plan.is_empty());
 		assert_eq!(old_key_version_owners, plan.consensus_group);
 		assert_eq!(new_nodes_set, plan.new_nodes_map.keys().cloned().collect());
 	}
+
+	#[test]
+	fn share_change_plan_does_not_add_readonly_nodes() {
+		let cluster_nodes: Vec<_> = (0..4).map(|_| math::generate_random_point().unwrap()).collect();
+		let master = cluster_nodes[0].clone();
+		let old_key_version_owners = cluster_nodes[0..2].iter().cloned().collect();
+		let readonly_nodes = ::std::iter::once(cluster_nodes[3].clone()).collect();
+		let new_nodes_set = cluster_nodes.iter().cloned().collect();
+		let plan = prepare_share_change_session_plan(&cluster_nodes.iter().cloned().collect(), &readonly_nodes,
+			1, &Default::default(), Default::default(), &master, &old_key_version_owners, &new_nodes_set).unwrap();
+
+		assert!(!plan.is_empty());
+		assert_eq!(cluster_nodes[0..3].iter().cloned().collect::<BTreeSet<_>>(), plan.new_nodes_map.keys().cloned().collect());
+	}
 }
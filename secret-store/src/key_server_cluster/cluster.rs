@@ -45,6 +45,7 @@ use key_server_cluster::io::{DeadlineStatus, ReadMessage, SharedTcpStream, read_
 use key_server_cluster::net::{accept_connection as net_accept_connection, connect as net_connect, Connection as NetConnection};
 use key_server_cluster::connection_trigger::{Maintain, ConnectionTrigger, SimpleConnectionTrigger, ServersSetChangeSessionCreatorConnector};
 use key_server_cluster::connection_trigger_with_migration::ConnectionTriggerWithMigration;
+use key_server_cluster::servers_set_change_session::{ServersSetChangeConfiguration, ServersSetChangeOptions};
 
 /// Maintain interval (seconds). Every MAINTAIN_INTERVAL seconds node:
 /// 1) checks if connected nodes are responding to KeepAlive messages
@@ -79,7 +80,7 @@ pub trait ClusterClient: Send + Sync {
 	/// Start new key version negotiation session.
 	fn new_key_version_negotiation_session(&self, session_id: SessionId) -> Result<Arc<KeyVersionNegotiationSession<KeyVersionNegotiationSessionTransport>>, Error>;
 	/// Start new servers set change session.
-	fn new_servers_set_change_session(&self, session_id: Option<SessionId>, migration_id: Option<H256>, new_nodes_set: BTreeSet<NodeId>, old_set_signature: Signature, new_set_signature: Signature) -> Result<Arc<AdminSession>, Error>;
+	fn new_servers_set_change_session(&self, session_id: Option<SessionId>, migration_id: Option<H256>, new_nodes_set: BTreeSet<NodeId>, old_set_signature: Signature, new_set_signature: Signature, options: ServersSetChangeOptions) -> Result<Arc<AdminSession>, Error>;
 
 	/// Listen for new generation sessions.
 	fn add_generation_listener(&self, listener: Arc<ClusterSessionsListener<GenerationSession>>);
@@ -139,6 +140,8 @@ pub struct ClusterConfiguration {
 	/// will only work when servers set is configured using KeyServerSet
 	/// contract.
 	pub auto_migrate_enabled: bool,
+	/// Servers set change sessions configuration.
+	pub servers_set_change: ServersSetChangeConfiguration,
 }
 
 /// Cluster state.
@@ -875,6 +878,18 @@ impl ClusterView {
 			})),
 		}
 	}
+
+	/// Send message to the node in background, logging network error (if any).
+	fn spawn_send_message(cluster: &ClusterData, connection: &Connection, message: Message) {
+		let self_node_id = cluster.self_key_pair.public().clone();
+		let node_id = connection.node_id().clone();
+		cluster.spawn(connection.send_message(message).then(move |result| {
+			if let Err(err) = result {
+				warn!(target: "secretstore_net", "{}: network error '{}' when sending message to node {}", self_node_id, err, node_id);
+			}
+			Ok(())
+		}));
+	}
 }
 
 impl Cluster for ClusterView {
@@ -883,7 +898,7 @@ impl Cluster for ClusterView {
 		for node in core.nodes.iter().filter(|n| *n != core.cluster.self_key_pair.public()) {
 			trace!(target: "secretstore_net", "{}: sent message {} to {}", core.cluster.self_key_pair.public(), message, node);
 			let connection = core.cluster.connection(node).ok_or(Error::NodeDisconnected)?;
-			core.cluster.spawn(connection.send_message(message.clone()).then(|_| Ok(())))
+			Self::spawn_send_message(&core.cluster, &connection, message.clone());
 		}
 		Ok(())
 	}
@@ -892,7 +907,7 @@ impl Cluster for ClusterView {
 		let core = self.core.read();
 		trace!(target: "secretstore_net", "{}: sent message {} to {}", core.cluster.self_key_pair.public(), message, to);
 		let connection = core.cluster.connection(to).ok_or(Error::NodeDisconnected)?;
-		core.cluster.spawn(connection.send_message(message).then(|_| Ok(())));
+		Self::spawn_send_message(&core.cluster, &connection, message);
 		Ok(())
 	}
 
@@ -1060,7 +1075,7 @@ impl ClusterClient for ClusterClientImpl {
 		Ok(session)
 	}
 
-	fn new_servers_set_change_session(&self, session_id: Option<SessionId>, migration_id: Option<H256>, new_nodes_set: BTreeSet<NodeId>, old_set_signature: Signature, new_set_signature: Signature) -> Result<Arc<AdminSession>, Error> {
+	fn new_servers_set_change_session(&self, session_id: Option<SessionId>, migration_id: Option<H256>, new_nodes_set: BTreeSet<NodeId>, old_set_signature: Signature, new_set_signature: Signature, options: ServersSetChangeOptions) -> Result<Arc<AdminSession>, Error> {
 		let mut connected_nodes = self.data.connections.connected_nodes()?;
 		connected_nodes.insert(self.data.self_key_pair.public().clone());
 
@@ -1071,7 +1086,7 @@ impl ClusterClient for ClusterClientImpl {
 		};
 
 		let cluster = create_cluster_view(&self.data, true)?;
-		let creation_data = Some(AdminSessionCreationData::ServersSetChange(migration_id, new_nodes_set.clone()));
+		let creation_data = Some(AdminSessionCreationData::ServersSetChange(migration_id, new_nodes_set.clone(), options));
 		let session = self.data.sessions.admin_sessions.insert(cluster, self.data.self_key_pair.public().clone(), session_id, None, true, creation_data)?;
 		let initialization_result = session.as_servers_set_change().expect("servers set change session is created; qed")
 			.initialize(new_nodes_set, old_set_signature, new_set_signature);
@@ -1144,6 +1159,7 @@ pub mod tests {
 	use key_server_cluster::message::Message;
 	use key_server_cluster::cluster::{Cluster, ClusterCore, ClusterConfiguration, ClusterClient, ClusterState};
 	use key_server_cluster::cluster_sessions::{ClusterSession, AdminSession, ClusterSessionsListener};
+	use key_server_cluster::servers_set_change_session::ServersSetChangeOptions;
 	use key_server_cluster::generation_session::{SessionImpl as GenerationSession, SessionState as GenerationSessionState};
 	use key_server_cluster::decryption_session::{SessionImpl as DecryptionSession};
 	use key_server_cluster::encryption_session::{SessionImpl as EncryptionSession};
@@ -1183,7 +1199,7 @@ pub mod tests {
 		fn new_ecdsa_signing_session(&self, _session_id: SessionId, _requester: Requester, _version: Option<H256>, _message_hash: H256) -> Result<Arc<EcdsaSigningSession>, Error> { unimplemented!("test-only") }
 
 		fn new_key_version_negotiation_session(&self, _session_id: SessionId) -> Result<Arc<KeyVersionNegotiationSession<KeyVersionNegotiationSessionTransport>>, Error> { unimplemented!("test-only") }
-		fn new_servers_set_change_session(&self, _session_id: Option<SessionId>, _migration_id: Option<H256>, _new_nodes_set: BTreeSet<NodeId>, _old_set_signature: Signature, _new_set_signature: Signature) -> Result<Arc<AdminSession>, Error> { unimplemented!("test-only") }
+		fn new_servers_set_change_session(&self, _session_id: Option<SessionId>, _migration_id: Option<H256>, _new_nodes_set: BTreeSet<NodeId>, _old_set_signature: Signature, _new_set_signature: Signature, _options: ServersSetChangeOptions) -> Result<Arc<AdminSession>, Error> { unimplemented!("test-only") }
 
 		fn add_generation_listener(&self, _listener: Arc<ClusterSessionsListener<GenerationSession>>) {}
 		fn add_decryption_listener(&self, _listener: Arc<ClusterSessionsListener<DecryptionSession>>) {}
@@ -1307,6 +1323,7 @@ pub mod tests {
 			acl_storage: Arc::new(DummyAclStorage::default()),
 			admin_public: None,
 			auto_migrate_enabled: false,
+			servers_set_change: Default::default(),
 		}).collect();
 		let clusters: Vec<_> = cluster_params.into_iter().enumerate()
 			.map(|(_, params)| ClusterCore::new(runtime.executor(), params).unwrap())
@@ -31,7 +31,7 @@ use key_server_cluster::encryption_session::{SessionImpl as EncryptionSessionImp
 use key_server_cluster::signing_session_ecdsa::{SessionImpl as EcdsaSigningSessionImpl};
 use key_server_cluster::signing_session_schnorr::{SessionImpl as SchnorrSigningSessionImpl};
 use key_server_cluster::share_add_session::{SessionImpl as ShareAddSessionImpl, IsolatedSessionTransport as ShareAddTransport};
-use key_server_cluster::servers_set_change_session::{SessionImpl as ServersSetChangeSessionImpl};
+use key_server_cluster::servers_set_change_session::{SessionImpl as ServersSetChangeSessionImpl, ServersSetChangeOptions};
 use key_server_cluster::key_version_negotiation_session::{SessionImpl as KeyVersionNegotiationSessionImpl,
 	IsolatedSessionTransport as VersionNegotiationTransport};
 
@@ -114,8 +114,8 @@ pub enum AdminSession {
 pub enum AdminSessionCreationData {
 	/// Share add session (key id).
 	ShareAdd(H256),
-	/// Servers set change session (block id, new_server_set).
-	ServersSetChange(Option<H256>, BTreeSet<NodeId>),
+	/// Servers set change session (block id, new_server_set, session options).
+	ServersSetChange(Option<H256>, BTreeSet<NodeId>, ServersSetChangeOptions),
 }
 
 /// Active sessions on this cluster.
@@ -218,6 +218,7 @@ impl ClusterSessions {
 			admin_sessions: ClusterSessionsContainer::new(AdminSessionCreator {
 				core: creator_core.clone(),
 				servers_set_change_session_creator_connector: servers_set_change_session_creator_connector,
+				servers_set_change_config: config.servers_set_change.clone(),
 				admin_public: config.admin_public.clone(),
 			}, container_state),
 			creator_core: creator_core,
@@ -572,14 +573,19 @@ mod tests {
 	use std::sync::atomic::{AtomicUsize, Ordering};
 	use ethkey::{Random, Generator};
 	use key_server_cluster::{Error, DummyAclStorage, DummyKeyStorage, MapKeyServerSet, PlainNodeKeyPair};
-	use key_server_cluster::cluster::ClusterConfiguration;
+	use key_server_cluster::cluster::{Cluster, ClusterConfiguration};
 	use key_server_cluster::connection_trigger::SimpleServersSetChangeSessionCreatorConnector;
 	use key_server_cluster::cluster::tests::DummyCluster;
 	use key_server_cluster::generation_session::{SessionImpl as GenerationSession};
+	use key_server_cluster::servers_set_change_session::ServersSetChangeConfiguration;
 	use super::{ClusterSessions, AdminSessionCreationData, ClusterSessionsListener,
 		ClusterSessionsContainerState, SESSION_TIMEOUT_INTERVAL};
 
 	pub fn make_cluster_sessions() -> ClusterSessions {
+		make_cluster_sessions_with_config(Default::default())
+	}
+
+	pub fn make_cluster_sessions_with_config(servers_set_change: ServersSetChangeConfiguration) -> ClusterSessions {
 		let key_pair = Random.generate().unwrap();
 		let config = ClusterConfiguration {
 			self_key_pair: Arc::new(PlainNodeKeyPair::new(key_pair.clone())),
@@ -590,6 +596,7 @@ mod tests {
 			acl_storage: Arc::new(DummyAclStorage::default()),
 			admin_public: Some(Random.generate().unwrap().public().clone()),
 			auto_migrate_enabled: false,
+			servers_set_change: servers_set_change,
 		};
 		ClusterSessions::new(&config, Arc::new(SimpleServersSetChangeSessionCreatorConnector {
 			admin_public: Some(Random.generate().unwrap().public().clone()),
@@ -619,6 +626,38 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn servers_set_change_session_is_created_using_configuration() {
+		// readonly node is not known to the cluster => session can't be created
+		let readonly_node_id = Random.generate().unwrap().public().clone();
+		let sessions = make_cluster_sessions_with_config(ServersSetChangeConfiguration {
+			readonly_nodes: ::std::iter::once(readonly_node_id.clone()).collect(),
+			..Default::default()
+		});
+		let cluster = Arc::new(DummyCluster::new(sessions.self_node_id.clone()));
+		cluster.add_node(sessions.self_node_id.clone());
+		let creation_data = || Some(AdminSessionCreationData::ServersSetChange(None, cluster.nodes(), Default::default()));
+		assert_eq!(sessions.admin_sessions.insert(cluster.clone(), sessions.self_node_id.clone(), Default::default(), None, true, creation_data()).err(),
+			Some(Error::InvalidNodesConfiguration));
+
+		// readonly node is known to the cluster => session is created
+		cluster.add_node(readonly_node_id);
+		assert!(sessions.admin_sessions.insert(cluster.clone(), sessions.self_node_id.clone(), Default::default(), None, true, creation_data()).is_ok());
+	}
+
+	#[test]
+	fn servers_set_change_session_is_created_using_configured_sessions_limit() {
+		let sessions = make_cluster_sessions_with_config(ServersSetChangeConfiguration {
+			max_active_sessions: 0,
+			..Default::default()
+		});
+		let cluster = Arc::new(DummyCluster::new(sessions.self_node_id.clone()));
+		cluster.add_node(sessions.self_node_id.clone());
+		assert_eq!(sessions.admin_sessions.insert(cluster.clone(), sessions.self_node_id.clone(), Default::default(), None, true,
+			Some(AdminSessionCreationData::ServersSetChange(None, cluster.nodes(), Default::default()))).err(),
+			Some(Error::InvalidNodesConfiguration));
+	}
+
 	#[test]
 	fn session_listener_works() {
 		#[derive(Default)]
@@ -36,7 +36,7 @@ use key_server_cluster::signing_session_schnorr::{SessionImpl as SchnorrSigningS
 use key_server_cluster::share_add_session::{SessionImpl as ShareAddSessionImpl,
 	SessionParams as ShareAddSessionParams, IsolatedSessionTransport as ShareAddTransport};
 use key_server_cluster::servers_set_change_session::{SessionImpl as ServersSetChangeSessionImpl,
-	SessionParams as ServersSetChangeSessionParams};
+	SessionParams as ServersSetChangeSessionParams, ServersSetChangeConfiguration};
 use key_server_cluster::key_version_negotiation_session::{SessionImpl as KeyVersionNegotiationSessionImpl,
 	SessionParams as KeyVersionNegotiationSessionParams, IsolatedSessionTransport as VersionNegotiationTransport,
 	FastestResultComputer as FastestResultKeyVersionsResultComputer};
@@ -396,6 +396,8 @@ pub struct AdminSessionCreator {
 	pub admin_public: Option<Public>,
 	/// Servers set change sessions creator connector.
 	pub servers_set_change_session_creator_connector: Arc<ServersSetChangeSessionCreatorConnector>,
+	/// Servers set change sessions configuration.
+	pub servers_set_change_config: ServersSetChangeConfiguration,
 }
 
 impl ClusterSessionCreator<AdminSession, AdminSessionCreationData> for AdminSessionCreator {
@@ -404,7 +406,8 @@ impl ClusterSessionCreator<AdminSession, AdminSessionCreationData> for AdminSess
 			Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeConsensusMessage(ref message)) => match &message.message {
 				&ConsensusMessageWithServersSet::InitializeConsensusSession(ref message) => Ok(Some(AdminSessionCreationData::ServersSetChange(
 					message.migration_id.clone().map(Into::into),
-					message.new_nodes_set.clone().into_iter().map(Into::into).collect()
+					message.new_nodes_set.clone().into_iter().map(Into::into).collect(),
+					Default::default(),
 				))),
 				_ => Err(Error::InvalidMessage),
 			},
@@ -442,7 +445,7 @@ impl ClusterSessionCreator<AdminSession, AdminSessionCreationData> for AdminSess
 					admin_public: Some(self.admin_public.clone().ok_or(Error::AccessDenied)?),
 				})?)
 			},
-			Some(AdminSessionCreationData::ServersSetChange(migration_id, new_nodes_set)) => {
+			Some(AdminSessionCreationData::ServersSetChange(migration_id, new_nodes_set, options)) => {
 				let admin_public = self.servers_set_change_session_creator_connector.admin_public(migration_id.as_ref(), new_nodes_set)
 					.map_err(|_| Error::AccessDenied)?;
 
@@ -458,8 +461,18 @@ impl ClusterSessionCreator<AdminSession, AdminSessionCreationData> for AdminSess
 					key_storage: self.core.key_storage.clone(),
 					nonce: nonce,
 					all_nodes_set: cluster.nodes(),
-					admin_public: admin_public,
+					admin_publics: ::std::iter::once(admin_public).collect(),
 					migration_id: migration_id,
+					key_priorities: options.key_priorities,
+					max_active_sessions: self.servers_set_change_config.max_active_sessions,
+					dispatch_budget: self.servers_set_change_config.dispatch_budget,
+					progress: options.progress,
+					dry_run: options.dry_run,
+					target_keys: options.target_keys,
+					precheck_connectivity: self.servers_set_change_config.precheck_connectivity,
+					node_scorer: self.servers_set_change_config.node_scorer.clone(),
+					readonly_nodes: self.servers_set_change_config.readonly_nodes.clone(),
+					metrics: self.servers_set_change_config.metrics.clone(),
 				})?)
 			},
 			None => unreachable!("expected to call with non-empty creation data; qed"),
@@ -271,7 +271,7 @@ impl TriggerSession {
 					.map(|new_set_signature| (old_set_signature, new_set_signature)))
 				.map_err(Into::into);
 			let session = signatures.and_then(|(old_set_signature, new_set_signature)|
-				sessions.new_servers_set_change_session(None, Some(migration.id.clone()), new_set, old_set_signature, new_set_signature));
+				sessions.new_servers_set_change_session(None, Some(migration.id.clone()), new_set, old_set_signature, new_set_signature, Default::default()));
 
 			match session {
 				Ok(_) => trace!(target: "secretstore_net", "{}: started auto-migrate session",
@@ -308,9 +308,9 @@ pub mod tests {
 	use ethkey::{Random, Generator, KeyPair};
 	use ethkey::crypto::ecdh::agree;
 	use key_server_cluster::Error;
-	use key_server_cluster::message::Message;
+	use key_server_cluster::message::{Message, ServersSetChangeMessage, ServersSetChangeError};
 	use super::{MESSAGE_HEADER_SIZE, CURRENT_HEADER_VERSION, MessageHeader, fix_shared_key, encrypt_message,
-		serialize_message, serialize_header, deserialize_header};
+		serialize_message, deserialize_message, serialize_header, deserialize_header};
 
 	pub struct TestIo {
 		self_key_pair: KeyPair,
@@ -426,4 +426,23 @@ pub mod tests {
 
 		assert_eq!(deserialize_header(&serialize_header(&header).unwrap()).unwrap_err(), Error::InvalidMessageVersion);
 	}
+
+	#[test]
+	fn session_errors_survive_serialization() {
+		for error in vec![Error::Timeout, Error::Cancelled, Error::NodeDisconnected,
+			Error::ShareChangeFailed(42u64.into(), Box::new(Error::ShareChangeFailed(Default::default(), Box::new(Error::InvalidMessage))))] {
+			let message = Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeError(ServersSetChangeError {
+				session: Default::default(),
+				session_nonce: 1,
+				error: error.clone(),
+			}));
+
+			let serialized_message: Vec<_> = serialize_message(message).unwrap().into();
+			let header = deserialize_header(&serialized_message[..MESSAGE_HEADER_SIZE]).unwrap();
+			match deserialize_message(&header, serialized_message[MESSAGE_HEADER_SIZE..].to_vec()).unwrap() {
+				Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeError(message)) => assert_eq!(message.error, error),
+				_ => panic!("unexpected message"),
+			}
+		}
+	}
 }
@@ -23,8 +23,9 @@ use key_server_cluster::jobs::job_session::{JobPartialResponseAction, JobPartial
 
 /// Purpose of this job is to check if requestor is administrator of SecretStore (i.e. it have access to change key servers set).
 pub struct ServersSetChangeAccessJob {
-	/// Servers set administrator public key (this could be changed to ACL-based check later).
-	administrator: Public,
+	/// Servers set administrators public keys (this could be changed to ACL-based check later).
+	/// More than one key is accepted when administrator key is rotated.
+	administrators: BTreeSet<Public>,
 	/// Old servers set.
 	old_servers_set: Option<BTreeSet<NodeId>>,
 	/// New servers set.
@@ -70,9 +71,9 @@ impl<'a> From<&'a InitializeConsensusSessionOfShareAdd> for ServersSetChangeAcce
 }
 
 impl ServersSetChangeAccessJob {
-	pub fn new_on_slave(administrator: Public) -> Self {
+	pub fn new_on_slave(administrators: BTreeSet<Public>) -> Self {
 		ServersSetChangeAccessJob {
-			administrator: administrator,
+			administrators: administrators,
 			old_servers_set: None,
 			new_servers_set: None,
 			old_set_signature: None,
@@ -80,9 +81,9 @@ impl ServersSetChangeAccessJob {
 		}
 	}
 
-	pub fn new_on_master(administrator: Public, old_servers_set: BTreeSet<NodeId>, new_servers_set: BTreeSet<NodeId>, old_set_signature: Signature, new_set_signature: Signature) -> Self {
+	pub fn new_on_master(administrators: BTreeSet<Public>, old_servers_set: BTreeSet<NodeId>, new_servers_set: BTreeSet<NodeId>, old_set_signature: Signature, new_set_signature: Signature) -> Self {
 		ServersSetChangeAccessJob {
-			administrator: administrator,
+			administrators: administrators,
 			old_servers_set: Some(old_servers_set),
 			new_servers_set: Some(new_servers_set),
 			old_set_signature: Some(old_set_signature),
@@ -121,7 +122,7 @@ impl JobExecutor for ServersSetChangeAccessJob {
 		// check old servers set signature
 		let old_actual_public = recover(&old_set_signature, &ordered_nodes_hash(&old_servers_set).into())?;
 		let new_actual_public = recover(&new_set_signature, &ordered_nodes_hash(&new_servers_set).into())?;
-		let is_administrator = old_actual_public == self.administrator && new_actual_public == self.administrator;
+		let is_administrator = self.administrators.contains(&old_actual_public) && self.administrators.contains(&new_actual_public);
 		self.new_servers_set = Some(new_servers_set);
 
 		Ok(if is_administrator { JobPartialRequestAction::Respond(true) } else { JobPartialRequestAction::Reject(false) })
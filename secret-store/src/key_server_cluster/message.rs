@@ -950,6 +950,9 @@ pub struct ServersSetChangeCompleted {
 	pub session: MessageSessionId,
 	/// Session-level nonce.
 	pub session_nonce: u64,
+	/// Is it a dry run (i.e. no changes have been made && removed nodes must keep their databases)?
+	#[serde(default)]
+	pub is_dry_run: bool,
 }
 
 /// Consensus-related share add session message.
@@ -1511,6 +1514,7 @@ impl fmt::Display for EcdsaSigningMessage {
 
 impl fmt::Display for ServersSetChangeMessage {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		// only message name && session-level data are printed => secret data (like key shares) is never printed
 		match *self {
 			ServersSetChangeMessage::ServersSetChangeConsensusMessage(ref m) => write!(f, "ServersSetChangeConsensusMessage.{}", m.message),
 			ServersSetChangeMessage::UnknownSessionsRequest(_) => write!(f, "UnknownSessionsRequest"),
@@ -1523,7 +1527,9 @@ impl fmt::Display for ServersSetChangeMessage {
 			ServersSetChangeMessage::ServersSetChangeShareAddMessage(ref m) => write!(f, "ServersSetChangeShareAddMessage.{}", m.message),
 			ServersSetChangeMessage::ServersSetChangeError(_) => write!(f, "ServersSetChangeError"),
 			ServersSetChangeMessage::ServersSetChangeCompleted(_) => write!(f, "ServersSetChangeCompleted"),
-		}
+		}?;
+
+		write!(f, " (session: {}, nonce: {})", self.session_id(), self.session_nonce())
 	}
 }
 
@@ -1548,3 +1554,56 @@ impl fmt::Display for KeyVersionNegotiationMessage {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use rustc_hex::ToHex;
+	use key_server_cluster::math;
+	use key_server_cluster::SessionId;
+	use super::{Message, GenerationMessage, KeysDissemination, ServersSetChangeMessage, ServersSetChangeShareAddMessage,
+		ShareAddMessage, NewKeysDissemination};
+
+	#[test]
+	fn servers_set_change_message_display_does_not_include_secret_data() {
+		let secret_subshare = math::generate_random_scalar().unwrap();
+		let message = Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeShareAddMessage(ServersSetChangeShareAddMessage {
+			session: SessionId::from(42).into(),
+			session_nonce: 777,
+			message: ShareAddMessage::NewKeysDissemination(NewKeysDissemination {
+				session: SessionId::from(1).into(),
+				session_nonce: 0,
+				secret_subshare: secret_subshare.clone().into(),
+			}),
+		}));
+
+		let message = format!("{}", message);
+		assert_eq!(message, format!("ServersSetChange.ServersSetChangeShareAddMessage.NewKeysDissemination (session: {}, nonce: 777)",
+			SessionId::from(42)));
+		assert!(!message.contains(&secret_subshare.to_hex()));
+	}
+
+	#[test]
+	fn messages_debug_does_not_include_secret_data() {
+		let secret1 = math::generate_random_scalar().unwrap();
+		let secret2 = math::generate_random_scalar().unwrap();
+		let message = format!("{:?}", Message::Generation(GenerationMessage::KeysDissemination(KeysDissemination {
+			session: SessionId::from(1).into(),
+			session_nonce: 0,
+			secret1: secret1.clone().into(),
+			secret2: secret2.clone().into(),
+			publics: Vec::new(),
+		})));
+		assert!(message.contains("secret1: <redacted>"));
+		assert!(!message.contains(&secret1.to_hex()));
+		assert!(!message.contains(&secret2.to_hex()));
+
+		let secret_subshare = math::generate_random_scalar().unwrap();
+		let message = format!("{:?}", ShareAddMessage::NewKeysDissemination(NewKeysDissemination {
+			session: SessionId::from(1).into(),
+			session_nonce: 0,
+			secret_subshare: secret_subshare.clone().into(),
+		}));
+		assert!(message.contains("secret_subshare: <redacted>"));
+		assert!(!message.contains(&secret_subshare.to_hex()));
+	}
+}
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity Ethereum.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::fmt;
 use std::collections::BTreeMap;
 use std::sync::Arc;
 use serde_json;
@@ -52,7 +53,7 @@ pub struct DocumentKeyShare {
 }
 
 /// Versioned portion of document key share.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Clone, PartialEq)]
 pub struct DocumentKeyShareVersion {
 	/// Version hash (Keccak(time + id_numbers)).
 	pub hash: H256,
@@ -78,6 +79,8 @@ pub trait KeyStorage: Send + Sync {
 	fn contains(&self, document: &ServerKeyId) -> bool;
 	/// Iterate through storage
 	fn iter<'a>(&'a self) -> Box<Iterator<Item=(ServerKeyId, DocumentKeyShare)> + 'a>;
+	/// Flush buffered changes to the durable storage
+	fn flush(&self) -> Result<(), Error>;
 }
 
 /// Persistent document encryption keys storage
@@ -308,6 +311,10 @@ impl KeyStorage for PersistentKeyStorage {
 			iter: self.db.iter(None),
 		})
 	}
+
+	fn flush(&self) -> Result<(), Error> {
+		self.db.flush().map_err(Into::into)
+	}
 }
 
 impl<'a> Iterator for PersistentKeyStorageIterator<'a> {
@@ -364,6 +371,17 @@ impl DocumentKeyShareVersion {
 	}
 }
 
+impl fmt::Debug for DocumentKeyShareVersion {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		// secret share is never printed => verbose logging can't leak key shares
+		f.debug_struct("DocumentKeyShareVersion")
+			.field("hash", &self.hash)
+			.field("id_numbers", &self.id_numbers)
+			.field("secret_share", &format_args!("<redacted>"))
+			.finish()
+	}
+}
+
 impl From<DocumentKeyShare> for SerializableDocumentKeyShareV3 {
 	fn from(key: DocumentKeyShare) -> Self {
 		SerializableDocumentKeyShareV3 {
@@ -414,6 +432,7 @@ pub mod tests {
 	use std::sync::Arc;
 	use parking_lot::RwLock;
 	use serde_json;
+	use rustc_hex::ToHex;
 	use self::tempdir::TempDir;
 	use ethereum_types::{Address, H256};
 	use ethkey::{Random, Generator, Public, Secret, public_to_address};
@@ -461,6 +480,10 @@ pub mod tests {
 		fn iter<'a>(&'a self) -> Box<Iterator<Item=(ServerKeyId, DocumentKeyShare)> + 'a> {
 			Box::new(self.keys.read().clone().into_iter())
 		}
+
+		fn flush(&self) -> Result<(), Error> {
+			Ok(())
+		}
 	}
 
 	#[test]
@@ -648,4 +671,24 @@ pub mod tests {
 
 		assert_eq!("00125d85a05e5e63e214cb60fe63f132eec8a103aa29266b7e6e6c5b7597230b".parse::<Secret>().unwrap(), key.versions[0].secret_share.clone().into());
 	}
+
+	#[test]
+	fn document_key_share_debug_does_not_include_secret_share() {
+		let secret_share = Random.generate().unwrap().secret().clone();
+		let key_share = DocumentKeyShare {
+			author: Default::default(),
+			threshold: 1,
+			public: Random.generate().unwrap().public().clone(),
+			common_point: None,
+			encrypted_point: None,
+			versions: vec![DocumentKeyShareVersion::new(
+				vec![(Random.generate().unwrap().public().clone(), Random.generate().unwrap().secret().clone())].into_iter().collect(),
+				secret_share.clone(),
+			)],
+		};
+
+		let key_share = format!("{:?}", key_share);
+		assert!(key_share.contains("secret_share: <redacted>"));
+		assert!(!key_share.contains(&secret_share.to_hex()));
+	}
 }
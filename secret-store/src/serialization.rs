@@ -35,7 +35,7 @@ macro_rules! impl_bytes_deserialize {
 
 macro_rules! impl_bytes {
 	($name: ident, $other: ident, $from_hex: ident, ($($trait: ident),*)) => {
-		#[derive(Clone, Debug, PartialEq, Eq, $($trait,)*)]
+		#[derive(Clone, PartialEq, Eq, $($trait,)*)]
 		pub struct $name(pub $other);
 
 		impl<T> From<T> for $name where $other: From<T> {
@@ -102,17 +102,24 @@ pub type SerializableMessageHash = SerializableH256;
 pub type SerializableAddress = SerializableH160;
 
 /// Serializable Bytes.
-impl_bytes!(SerializableBytes, Bytes, true, (Default));
+impl_bytes!(SerializableBytes, Bytes, true, (Debug, Default));
 /// Serializable H256.
-impl_bytes!(SerializableH256, H256, false, (Default, PartialOrd, Ord));
+impl_bytes!(SerializableH256, H256, false, (Debug, Default, PartialOrd, Ord));
 /// Serializable H160.
-impl_bytes!(SerializableH160, H160, false, (Default));
+impl_bytes!(SerializableH160, H160, false, (Debug, Default));
 /// Serializable H512 (aka Public).
-impl_bytes!(SerializablePublic, Public, false, (Default, PartialOrd, Ord));
+impl_bytes!(SerializablePublic, Public, false, (Debug, Default, PartialOrd, Ord));
 /// Serializable Secret.
 impl_bytes!(SerializableSecret, Secret, false, ());
 /// Serializable Signature.
-impl_bytes!(SerializableSignature, Signature, false, ());
+impl_bytes!(SerializableSignature, Signature, false, (Debug));
+
+impl fmt::Debug for SerializableSecret {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		// secret is never printed => verbose logging of messages/key shares can't leak it
+		write!(f, "<redacted>")
+	}
+}
 
 /// Serializable shadow decryption result.
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -159,7 +166,9 @@ impl From<Requester> for SerializableRequester {
 #[cfg(test)]
 mod tests {
 	use serde_json;
-	use super::{SerializableBytes, SerializablePublic};
+	use ethkey::{Random, Generator};
+	use rustc_hex::ToHex;
+	use super::{SerializableBytes, SerializablePublic, SerializableSecret};
 
 	#[test]
 	fn serialize_and_deserialize_bytes() {
@@ -178,4 +187,12 @@ mod tests {
 		let public_deserialized: SerializablePublic = serde_json::from_str(&public_serialized).unwrap();
 		assert_eq!(public_deserialized, public);
 	}
+
+	#[test]
+	fn secret_debug_is_redacted() {
+		let secret = Random.generate().unwrap().secret().clone();
+		let debug = format!("{:?}", SerializableSecret(secret.clone()));
+		assert_eq!(debug, "<redacted>");
+		assert!(!debug.contains(&secret.to_hex()));
+	}
 }
@@ -19,6 +19,7 @@ use std::net;
 use std::io::Error as IoError;
 
 use {ethkey, crypto};
+use super::ServerKeyId;
 
 /// Secret store error.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -88,6 +89,19 @@ pub enum Error {
 	Database(String),
 	/// Internal error.
 	Internal(String),
+
+	// Variants below are not known to nodes, running previous versions of the secret store. Since errors
+	// are serialized by variant name, they are only decoded by upgraded nodes => new variants must be appended here.
+
+	/// Nodes configuration is invalid (like inconsistent nodes sets, duplicate id numbers of key share owners
+	/// or zero limit of concurrent share change sessions).
+	InvalidNodesConfiguration,
+	/// Session has not been completed within given time.
+	Timeout,
+	/// Session has been cancelled by administrator.
+	Cancelled,
+	/// Share change session of given key has failed during servers set change.
+	ShareChangeFailed(ServerKeyId, Box<Error>),
 }
 
 impl Error {
@@ -96,6 +110,9 @@ impl Error {
 	/// are affecting request processing), but about current SecretStore state.
 	pub fn is_non_fatal(&self) -> bool {
 		match *self {
+			// share change errors are as fatal as the error that has caused the failure
+			Error::ShareChangeFailed(_, ref error) => error.is_non_fatal(),
+
 			// non-fatal errors:
 
 			// session start errors => restarting session is a solution
@@ -109,17 +126,21 @@ impl Error {
 			// temporary (?) consensus problems, related to other non-fatal errors => restarting is probably (!) a solution
 			Error::ConsensusTemporaryUnreachable |
 			// exclusive session errors => waiting && restarting is a solution
-			Error::ExclusiveSessionActive | Error::HasActiveSessions => true,
+			Error::ExclusiveSessionActive | Error::HasActiveSessions |
+			// session is still running => waiting again is a solution
+			Error::Timeout => true,
 
 			// fatal errors:
 
 			// config-related errors
-			Error::InvalidNodeAddress | Error::InvalidNodeId |
+			Error::InvalidNodeAddress | Error::InvalidNodeId | Error::InvalidNodesConfiguration |
 			// wrong session input params errors
 			Error::NotEnoughNodesForThreshold | Error::ServerKeyAlreadyGenerated | Error::ServerKeyIsNotFound |
 				Error::DocumentKeyAlreadyStored | Error::DocumentKeyIsNotFound | Error::InsufficientRequesterData(_) |
 			// access denied/consensus error
 			Error::AccessDenied | Error::ConsensusUnreachable |
+			// session has been deliberately cancelled
+			Error::Cancelled |
 			// indeterminate internal errors, which could be either fatal (db failure, invalid request), or not (network error),
 			// but we still consider these errors as fatal
 			Error::EthKey(_) | Error::Serde(_) | Error::Hyper(_) | Error::Database(_) | Error::Internal(_) | Error::Io(_) => false,
@@ -158,6 +179,10 @@ impl fmt::Display for Error {
 			Error::Database(ref msg) => write!(f, "Database error: {}", msg),
 			Error::Internal(ref msg) => write!(f, "Internal error: {}", msg),
 			Error::Io(ref msg) => write!(f, "IO error: {}", msg),
+			Error::InvalidNodesConfiguration => write!(f, "Invalid nodes configuration"),
+			Error::Timeout => write!(f, "Session has not been completed in time"),
+			Error::Cancelled => write!(f, "Session was cancelled"),
+			Error::ShareChangeFailed(ref key_id, ref error) => write!(f, "Share change session of key {:?} failed: {}", key_id, error),
 		}
 	}
 }